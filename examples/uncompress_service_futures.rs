@@ -10,7 +10,6 @@ use smol::net::TcpListener;
 /// $ ncat localhost 1234 < tests/fixtures/file.txt.gz
 /// some_file_content
 /// ```
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     smol::block_on(async {
         let listener = TcpListener::bind("127.0.0.1:1234").await?;
@@ -6,8 +6,9 @@
 //! implementing the [`BlockingExecutor`] trait.
 
 use crate::{
-    ArchiveContents, ArchiveIteratorBuilder, ArchivePassword, DecodeCallback, Ownership, Result,
-    READER_BUFFER_SIZE,
+    iterator::{EntryFilterCallbackFn, IteratorOptions},
+    ArchiveContents, ArchiveIterator, ExtractOptions, ExtractReport, Ownership, ReadOptions,
+    Result, READER_BUFFER_SIZE,
 };
 use async_trait::async_trait;
 use futures_channel::mpsc::{channel, Receiver, Sender};
@@ -25,7 +26,8 @@ use std::{
     io::{ErrorKind, Read, Seek, SeekFrom, Write},
     path::Path,
     pin::Pin,
-    task::{Context, Poll},
+    sync::{Arc, Mutex, PoisonError},
+    task::{ready, Context, Poll, Waker},
 };
 
 #[async_trait]
@@ -188,12 +190,12 @@ impl Write for AsyncWriteWrapper {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         match block_on(self.tx.send(buf.to_owned())) {
             Ok(()) => Ok(buf.len()),
-            Err(err) => Err(std::io::Error::new(ErrorKind::Other, err)),
+            Err(err) => Err(std::io::Error::other(err)),
         }
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        block_on(self.tx.send(vec![])).map_err(|err| std::io::Error::new(ErrorKind::Other, err))
+        block_on(self.tx.send(vec![])).map_err(std::io::Error::other)
     }
 }
 
@@ -277,14 +279,15 @@ where
 /// Async version of
 /// [`list_archive_files_with_encoding`](crate::
 /// list_archive_files_with_encoding).
-pub async fn list_archive_files_with_encoding<B, R>(
+pub async fn list_archive_files_with_encoding<B, R, D>(
     blocking_executor: B,
     source: R,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<Vec<String>>
 where
     B: BlockingExecutor,
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     wrap_async_seek_read(blocking_executor, source, move |source| {
         crate::list_archive_files_with_encoding(source, decode)
@@ -301,17 +304,82 @@ where
     wrap_async_seek_read(blocking_executor, source, crate::list_archive_files).await?
 }
 
+/// Async version of
+/// [`list_archive_files_bytes`](crate::list_archive_files_bytes).
+pub async fn list_archive_files_bytes<B, R>(blocking_executor: B, source: R) -> Result<Vec<Vec<u8>>>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, crate::list_archive_files_bytes).await?
+}
+
+/// Async version of [`list_archive_files_os`](crate::list_archive_files_os).
+pub async fn list_archive_files_os<B, R>(
+    blocking_executor: B,
+    source: R,
+) -> Result<Vec<std::ffi::OsString>>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, crate::list_archive_files_os).await?
+}
+
+/// Async version of [`archive_encryption`](crate::archive_encryption).
+pub async fn archive_encryption<B, R>(
+    blocking_executor: B,
+    source: R,
+) -> Result<crate::ArchiveEncryption>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, crate::archive_encryption).await?
+}
+
+/// Async version of [`list_zip_encryption`](crate::list_zip_encryption).
+pub async fn list_zip_encryption<B, R>(
+    blocking_executor: B,
+    source: R,
+) -> Result<Vec<crate::ZipEntryEncryption>>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, crate::list_zip_encryption).await?
+}
+
+/// Async version of
+/// [`list_archive_files_with_options`](crate::
+/// list_archive_files_with_options).
+pub async fn list_archive_files_with_options<B, R>(
+    blocking_executor: B,
+    source: R,
+    options: ReadOptions<'static>,
+) -> Result<Vec<String>>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, move |source| {
+        crate::list_archive_files_with_options(source, options)
+    })
+    .await?
+}
+
 /// Async version of
 /// [`list_archive_entries_with_encoding`](crate::
 /// list_archive_entries_with_encoding).
-pub async fn list_archive_entries_with_encoding<B, R>(
+pub async fn list_archive_entries_with_encoding<B, R, D>(
     blocking_executor: B,
     source: R,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<Vec<crate::ArchiveEntryInfo>>
 where
     B: BlockingExecutor,
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     wrap_async_seek_read(blocking_executor, source, move |source| {
         crate::list_archive_entries_with_encoding(source, decode)
@@ -331,6 +399,24 @@ where
     wrap_async_seek_read(blocking_executor, source, crate::list_archive_entries).await?
 }
 
+/// Async version of
+/// [`list_archive_entries_with_options`](crate::
+/// list_archive_entries_with_options).
+pub async fn list_archive_entries_with_options<B, R>(
+    blocking_executor: B,
+    source: R,
+    options: ReadOptions<'static>,
+) -> Result<Vec<crate::ArchiveEntryInfo>>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    wrap_async_seek_read(blocking_executor, source, move |source| {
+        crate::list_archive_entries_with_options(source, options)
+    })
+    .await?
+}
+
 /// Async version of [`uncompress_data`](crate::uncompress_data).
 pub async fn uncompress_data<B, R, W>(blocking_executor: B, source: R, target: W) -> Result<usize>
 where
@@ -347,16 +433,17 @@ where
 /// Async version of
 /// [`uncompress_archive_with_encoding`](crate::
 /// uncompress_archive_with_encoding).
-pub async fn uncompress_archive_with_encoding<B, R>(
+pub async fn uncompress_archive_with_encoding<B, R, D>(
     blocking_executor: B,
     source: R,
     dest: &Path,
     ownership: Ownership,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<()>
 where
     B: BlockingExecutor,
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     let dest = dest.to_owned();
     wrap_async_seek_read(blocking_executor, source, move |source| {
@@ -383,20 +470,41 @@ where
     .await?
 }
 
+/// Async version of
+/// [`uncompress_archive_with_options`](crate::
+/// uncompress_archive_with_options).
+pub async fn uncompress_archive_with_options<B, R>(
+    blocking_executor: B,
+    source: R,
+    dest: &Path,
+    options: ExtractOptions<'static>,
+) -> Result<ExtractReport>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let dest = dest.to_owned();
+    wrap_async_seek_read(blocking_executor, source, move |source| {
+        crate::uncompress_archive_with_options(source, &dest, options)
+    })
+    .await?
+}
+
 /// Async version of
 /// [`uncompress_archive_file_with_encoding`](crate::
 /// uncompress_archive_file_with_encoding).
-pub async fn uncompress_archive_file_with_encoding<B, R, W>(
+pub async fn uncompress_archive_file_with_encoding<B, R, W, D>(
     blocking_executor: B,
     source: R,
     target: W,
     path: &str,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<usize>
 where
     B: BlockingExecutor,
     R: AsyncRead + AsyncSeek + Unpin,
     W: AsyncWrite + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     let path = path.to_owned();
     wrap_async_seek_read_and_write(blocking_executor, source, target, move |source, target| {
@@ -425,6 +533,169 @@ where
     .await?
 }
 
+/// Async version of
+/// [`uncompress_archive_file_with_options`](crate::
+/// uncompress_archive_file_with_options).
+pub async fn uncompress_archive_file_with_options<B, R, W>(
+    blocking_executor: B,
+    source: R,
+    target: W,
+    path: &str,
+    options: ReadOptions<'static>,
+) -> Result<usize>
+where
+    B: BlockingExecutor,
+    R: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let path = path.to_owned();
+    wrap_async_seek_read_and_write(blocking_executor, source, target, move |source, target| {
+        crate::uncompress_archive_file_with_options(source, target, &path, options)
+    })
+    .await?
+}
+
+// ----------------------------------------------------------------------------
+// Async entry reader
+// ----------------------------------------------------------------------------
+
+/// Forwards every write of the blocking extraction as a chunk to
+/// [`AsyncArchiveFileReader`]. The channel has no buffer, so a write blocks
+/// until the reader takes the previous chunk.
+struct ChannelWriter {
+    tx: Sender<Vec<u8>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match block_on(self.tx.send(buf.to_owned())) {
+            Ok(()) => Ok(buf.len()),
+            Err(err) => Err(std::io::Error::new(ErrorKind::BrokenPipe, err)),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn into_io_error(err: crate::Error) -> std::io::Error {
+    match err {
+        crate::Error::Io(err) => err,
+        err => std::io::Error::other(err),
+    }
+}
+
+/// Asynchronous reader over the content of a single archive entry.
+///
+/// The entry is extracted on a blocking worker which hands each chunk over
+/// as it is read from the reader, pausing until the previous chunk has been
+/// consumed. As with [`AsyncArchiveIterator`], progress only happens while
+/// the reader is polled, and dropping it stops the extraction. An entry
+/// missing from the archive is reported as an [`ErrorKind::NotFound`] error.
+pub struct AsyncArchiveFileReader {
+    rx: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+    worker: Option<Pin<Box<dyn Future<Output = Result<()>> + Send>>>,
+    pump: Option<Pin<Box<dyn Future<Output = Result<()>> + Send>>>,
+}
+
+impl AsyncRead for AsyncArchiveFileReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = &mut *self;
+        loop {
+            if this.position < this.chunk.len() {
+                let n = buf.len().min(this.chunk.len() - this.position);
+                buf[..n].copy_from_slice(&this.chunk[this.position..this.position + n]);
+                this.position += n;
+                return Poll::Ready(Ok(n));
+            }
+            if let Some(worker) = this.worker.as_mut() {
+                if let Poll::Ready(res) = worker.as_mut().poll(cx) {
+                    this.worker = None;
+                    if let Err(e) = res {
+                        return Poll::Ready(Err(into_io_error(e)));
+                    }
+                }
+            }
+            if let Some(pump) = this.pump.as_mut() {
+                if let Poll::Ready(res) = pump.as_mut().poll(cx) {
+                    this.pump = None;
+                    if let Err(e) = res {
+                        return Poll::Ready(Err(into_io_error(e)));
+                    }
+                }
+            }
+            match Pin::new(&mut this.rx).poll_next(cx) {
+                Poll::Ready(Some(chunk)) => {
+                    this.chunk = chunk;
+                    this.position = 0;
+                }
+                // The content is complete once the extraction has returned,
+                // which tells whether it failed.
+                Poll::Ready(None) if this.pump.is_none() => return Poll::Ready(Ok(0)),
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Open the entry at `path` of an archive as an [`AsyncRead`]. Nothing is
+/// read from `source` until the returned reader is polled.
+pub fn open_archive_file<B, R>(executor: B, source: R, path: &str) -> AsyncArchiveFileReader
+where
+    B: BlockingExecutor + 'static,
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    open_archive_file_with_options(executor, source, path, ReadOptions::new())
+}
+
+/// Like [`open_archive_file`], configuring the reader through
+/// [`ReadOptions`], e.g. to supply passwords.
+pub fn open_archive_file_with_options<B, R>(
+    _: B,
+    source: R,
+    path: &str,
+    options: ReadOptions<'static>,
+) -> AsyncArchiveFileReader
+where
+    B: BlockingExecutor + 'static,
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    let (tx, rx) = channel(0);
+    let (seekable_wrapper, seekable_worker) = make_seekable_read_wrapper_and_worker(source);
+    let path = path.to_owned();
+
+    let pump_fut = async move {
+        B::execute_blocking(move || {
+            crate::uncompress_archive_file_with_options(
+                seekable_wrapper,
+                ChannelWriter { tx },
+                &path,
+                options,
+            )
+        })
+        .await??;
+        Ok(())
+    };
+
+    AsyncArchiveFileReader {
+        rx,
+        chunk: Vec::new(),
+        position: 0,
+        worker: Some(Box::pin(seekable_worker)),
+        pump: Some(Box::pin(pump_fut)),
+    }
+}
+
 // ----------------------------------------------------------------------------
 // Async archive iterator
 // ----------------------------------------------------------------------------
@@ -481,9 +752,7 @@ impl Stream for AsyncArchiveIterator {
 
 pub(crate) fn new_async_archive_iterator<B, R>(
     source: R,
-    decode: DecodeCallback,
-    filter: Option<Box<AsyncEntryFilterCallbackFn>>,
-    password: Option<ArchivePassword>,
+    options: IteratorOptions<AsyncEntryFilterCallbackFn>,
 ) -> AsyncArchiveIterator
 where
     B: BlockingExecutor + 'static,
@@ -494,14 +763,15 @@ where
 
     let pump_fut = async move {
         let r: Result<()> = B::execute_blocking(move || -> Result<()> {
-            let mut builder = ArchiveIteratorBuilder::new(seekable_wrapper).decoder(decode);
-            if let Some(filter) = filter {
-                builder = builder.filter(move |name, stat| filter(name, stat));
-            }
-            if let Some(password) = password {
-                builder = builder.with_password(password);
-            }
-            let mut iter = builder.build()?;
+            let options = IteratorOptions {
+                filter: options
+                    .filter
+                    .map(|filter| filter as Box<EntryFilterCallbackFn>),
+                raw_format: options.raw_format,
+                mtree_format: options.mtree_format,
+                read_options: options.read_options,
+            };
+            let mut iter = ArchiveIterator::new(seekable_wrapper, options)?;
             for content in iter.by_ref() {
                 if block_on(entry_tx.send(content)).is_err() {
                     // Consumer dropped the receiver; stop forwarding and
@@ -522,3 +792,196 @@ where
         pump: Some(Box::pin(pump_fut)),
     }
 }
+
+// ----------------------------------------------------------------------------
+// Async entry stream
+// ----------------------------------------------------------------------------
+
+/// Path and `stat` of an entry yielded by [`AsyncEntryStream`].
+#[non_exhaustive]
+pub struct AsyncEntryMeta {
+    pub path: String,
+    pub stat: crate::stat,
+}
+
+/// An archive entry yielded by [`AsyncEntryStream`], with a reader over its
+/// content.
+#[non_exhaustive]
+pub struct AsyncEntry {
+    pub meta: AsyncEntryMeta,
+    pub reader: AsyncEntryReader,
+}
+
+struct EntryStreamState {
+    contents: AsyncArchiveIterator,
+    /// Item read past the end of an entry, handed to the stream next.
+    pending: Option<ArchiveContents>,
+    /// Whether the reader of the last entry is neither finished nor dropped.
+    reading: bool,
+    waker: Option<Waker>,
+}
+
+impl EntryStreamState {
+    fn poll_contents(&mut self, cx: &mut Context<'_>) -> Poll<Option<ArchiveContents>> {
+        match self.pending.take() {
+            Some(contents) => Poll::Ready(Some(contents)),
+            None => Pin::new(&mut self.contents).poll_next(cx),
+        }
+    }
+
+    fn finish_reading(&mut self) {
+        self.reading = false;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Asynchronous stream of the entries of an archive, each with an
+/// [`AsyncRead`] over its content.
+///
+/// Built on [`AsyncArchiveIterator`], whose chunks are handed to the reader
+/// of the current entry. The next entry is only yielded once that reader
+/// reached its end or was dropped; until then the stream is pending. The
+/// holes of sparse entries read as zeros.
+pub struct AsyncEntryStream {
+    state: Arc<Mutex<EntryStreamState>>,
+}
+
+impl AsyncEntryStream {
+    pub(crate) fn new(contents: AsyncArchiveIterator) -> AsyncEntryStream {
+        AsyncEntryStream {
+            state: Arc::new(Mutex::new(EntryStreamState {
+                contents,
+                pending: None,
+                reading: false,
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl Stream for AsyncEntryStream {
+    type Item = Result<AsyncEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.reading {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        loop {
+            match ready!(state.poll_contents(cx)) {
+                Some(ArchiveContents::StartOfEntry(path, stat)) => {
+                    state.reading = true;
+                    let reader = AsyncEntryReader {
+                        state: self.state.clone(),
+                        position: 0,
+                        chunk: Vec::new(),
+                        chunk_position: 0,
+                        hole: 0,
+                        done: false,
+                    };
+                    let meta = AsyncEntryMeta { path, stat };
+                    return Poll::Ready(Some(Ok(AsyncEntry { meta, reader })));
+                }
+                // Left over from a reader dropped before its end.
+                Some(ArchiveContents::DataChunk(..)) | Some(ArchiveContents::EndOfEntry) => {}
+                Some(ArchiveContents::Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Reader over the content of an [`AsyncEntry`].
+///
+/// Implements [`AsyncRead`], and with the `tokio_support` feature also
+/// `tokio::io::AsyncRead`.
+pub struct AsyncEntryReader {
+    state: Arc<Mutex<EntryStreamState>>,
+    position: u64,
+    chunk: Vec<u8>,
+    chunk_position: usize,
+    hole: u64,
+    done: bool,
+}
+
+impl AsyncEntryReader {
+    fn finish(&mut self, state: &mut EntryStreamState) {
+        self.done = true;
+        state.finish_reading();
+    }
+}
+
+impl AsyncRead for AsyncEntryReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = &mut *self;
+        loop {
+            if this.hole > 0 {
+                let n = buf.len().min(this.hole.try_into().unwrap_or(usize::MAX));
+                buf[..n].fill(0);
+                this.hole -= n as u64;
+                this.position += n as u64;
+                return Poll::Ready(Ok(n));
+            }
+            if this.chunk_position < this.chunk.len() {
+                let n = buf.len().min(this.chunk.len() - this.chunk_position);
+                buf[..n].copy_from_slice(&this.chunk[this.chunk_position..][..n]);
+                this.chunk_position += n;
+                this.position += n as u64;
+                return Poll::Ready(Ok(n));
+            }
+            if this.done {
+                return Poll::Ready(Ok(0));
+            }
+
+            let state = this.state.clone();
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            match ready!(state.poll_contents(cx)) {
+                Some(ArchiveContents::DataChunk(data, offset)) => {
+                    this.hole = offset.saturating_sub(this.position);
+                    this.chunk = data;
+                    this.chunk_position = 0;
+                }
+                Some(ArchiveContents::Err(e)) => {
+                    this.finish(&mut state);
+                    return Poll::Ready(Err(into_io_error(e)));
+                }
+                end => {
+                    if let Some(ArchiveContents::StartOfEntry(..)) = end {
+                        state.pending = end;
+                    }
+                    this.finish(&mut state);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio_support")]
+impl tokio::io::AsyncRead for AsyncEntryReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = ready!(AsyncRead::poll_read(self, cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for AsyncEntryReader {
+    fn drop(&mut self) {
+        if !self.done {
+            let state = self.state.clone();
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            self.finish(&mut state);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Filename charset detection for ZIP archives written without the UTF-8
+//! flag. Names declared as UTF-8 (general-purpose bit 11) or carrying an
+//! Info-ZIP Unicode Path extra field are trusted as-is; the remaining names
+//! are decoded with whichever candidate charset scores best across the whole
+//! central directory.
+
+use crate::{zip_preflight::CdEntry, DecodeCallback};
+use encoding_rs::{Encoding, GBK, SHIFT_JIS, WINDOWS_1252};
+use std::{collections::HashMap, sync::Arc};
+
+/// A legacy charset [`ZipCharsetDetector`] may pick for ZIP entry names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Charset {
+    /// IBM code page 437, the charset the ZIP specification assumes when the
+    /// UTF-8 flag is clear; used by DOS and many Western Windows tools.
+    Cp437,
+    /// Windows code page 1252 (Western European).
+    Cp1252,
+    /// GBK (Simplified Chinese, Windows code page 936).
+    Gbk,
+    /// Shift_JIS (Japanese, Windows code page 932).
+    ShiftJis,
+}
+
+impl Charset {
+    fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Charset::Cp437 => Some(bytes.iter().map(|&b| cp437_char(b)).collect()),
+            Charset::Cp1252 => decode_strict(WINDOWS_1252, bytes),
+            Charset::Gbk => decode_strict(GBK, bytes),
+            Charset::ShiftJis => decode_strict(SHIFT_JIS, bytes),
+        }
+    }
+
+    /// How plausible it is that `bytes` are a name in this charset. `None`
+    /// means the bytes are not valid in the charset at all.
+    fn score(self, bytes: &[u8]) -> Option<i64> {
+        let decoded = self.decode(bytes)?;
+        Some(match self {
+            Charset::Cp437 | Charset::Cp1252 => decoded.chars().map(single_byte_score).sum(),
+            Charset::Gbk => gbk_score(bytes),
+            Charset::ShiftJis => shift_jis_score(bytes),
+        })
+    }
+}
+
+/// Picks the charset of legacy ZIP entry names from a list of candidates.
+///
+/// Detection looks at every name in the central directory that is neither
+/// flagged as UTF-8 nor covered by a Unicode Path extra field. If all of them
+/// are valid UTF-8 they are decoded as such; otherwise each candidate is
+/// scored on how natural the decoded names look and the best one is used,
+/// earlier candidates winning ties. Inputs that are not ZIP archives keep
+/// using the configured decoder.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("legacy.zip")?;
+/// let options = ReadOptions::new().detect_zip_charset(
+///     ZipCharsetDetector::new().candidates([Charset::ShiftJis, Charset::Cp437]),
+/// );
+/// let file_list = list_archive_files_with_options(&mut source, options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[must_use]
+pub struct ZipCharsetDetector {
+    candidates: Vec<Charset>,
+}
+
+impl Default for ZipCharsetDetector {
+    fn default() -> Self {
+        ZipCharsetDetector {
+            candidates: vec![
+                Charset::Cp437,
+                Charset::Gbk,
+                Charset::ShiftJis,
+                Charset::Cp1252,
+            ],
+        }
+    }
+}
+
+impl ZipCharsetDetector {
+    /// Create a detector trying CP437, GBK, Shift_JIS and CP1252, in that
+    /// order.
+    pub fn new() -> ZipCharsetDetector {
+        ZipCharsetDetector::default()
+    }
+
+    /// Replace the candidate charsets. The order breaks ties between
+    /// candidates scoring equally.
+    pub fn candidates<I>(mut self, candidates: I) -> ZipCharsetDetector
+    where
+        I: IntoIterator<Item = Charset>,
+    {
+        self.candidates = candidates.into_iter().collect();
+        self
+    }
+
+    /// Build a decoder for the names in `entries`. Returns `None` when there
+    /// is nothing to detect from, so the caller keeps its own decoder.
+    pub(crate) fn decoder(
+        &self,
+        entries: Vec<CdEntry>,
+        fallback: Arc<DecodeCallback>,
+    ) -> Option<Arc<DecodeCallback>> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        let legacy: Vec<&[u8]> = entries
+            .iter()
+            .filter(|entry| !entry.is_utf8() && entry.unicode_path.is_none())
+            .map(|entry| entry.name.as_slice())
+            .filter(|name| !name.is_ascii())
+            .collect();
+        let charset = if legacy.iter().all(|name| std::str::from_utf8(name).is_ok()) {
+            None
+        } else {
+            self.detect(&legacy)
+        };
+
+        // Keyed on the name libarchive hands to the decoder: the Unicode Path
+        // extra field replaces the raw name when present.
+        let mut names = HashMap::with_capacity(entries.len());
+        for entry in entries {
+            if let Some(path) = entry.unicode_path {
+                names.insert(path.clone().into_bytes(), path);
+                continue;
+            }
+            let decoded = if entry.is_utf8() {
+                String::from_utf8(entry.name.clone()).ok()
+            } else {
+                decode_with(charset, &entry.name)
+            };
+            if let Some(decoded) = decoded {
+                names.insert(entry.name, decoded);
+            }
+        }
+
+        Some(Arc::new(move |bytes: &[u8]| match names.get(bytes) {
+            Some(name) => Ok(name.clone()),
+            // Names outside the central directory, e.g. symlink targets.
+            None => match decode_with(charset, bytes) {
+                Some(name) => Ok(name),
+                None => fallback(bytes),
+            },
+        }))
+    }
+
+    fn detect(&self, names: &[&[u8]]) -> Option<Charset> {
+        let mut best: Option<(Charset, i64)> = None;
+        for &charset in &self.candidates {
+            let score: Option<i64> = names.iter().map(|name| charset.score(name)).sum();
+            match (score, best) {
+                (Some(score), Some((_, best_score))) if score <= best_score => {}
+                (Some(score), _) => best = Some((charset, score)),
+                (None, _) => {}
+            }
+        }
+        best.map(|(charset, _)| charset)
+    }
+}
+
+fn decode_with(charset: Option<Charset>, bytes: &[u8]) -> Option<String> {
+    match charset {
+        Some(charset) => charset.decode(bytes),
+        None => std::str::from_utf8(bytes).ok().map(str::to_owned),
+    }
+}
+
+fn decode_strict(encoding: &'static Encoding, bytes: &[u8]) -> Option<String> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|name| name.into_owned())
+}
+
+// Latin letters are what a Western name is made of; box drawing, symbols and
+// C1 controls mostly show up when multi-byte text is read as a code page.
+fn single_byte_score(c: char) -> i64 {
+    match c {
+        c if c.is_ascii() => 0,
+        '\u{C0}'..='\u{24F}' if c.is_alphabetic() => 1,
+        c if c.is_alphabetic() => 0,
+        _ => -1,
+    }
+}
+
+// GB2312 hanzi (lead 0xB0-0xF7) are the common characters; lead bytes
+// 0xA1-0xA9 hold punctuation and kana, everything else is the rarely used
+// GBK extension.
+fn gbk_score(bytes: &[u8]) -> i64 {
+    let mut score = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        if lead < 0x80 {
+            i += 1;
+            continue;
+        }
+        if lead == 0x80 {
+            // Single-byte euro sign.
+            score -= 1;
+            i += 1;
+            continue;
+        }
+        let trail = bytes.get(i + 1).copied().unwrap_or(0);
+        if (0x30..=0x39).contains(&trail) {
+            // GB18030 four-byte sequence.
+            score -= 1;
+            i += 4;
+            continue;
+        }
+        score += match (lead, trail) {
+            (0xB0..=0xF7, 0xA1..) => 3,
+            (0xA1..=0xA9, 0xA1..) => 1,
+            _ => 0,
+        };
+        i += 2;
+    }
+    score
+}
+
+// Hiragana, katakana and level-1 kanji make up most Japanese names; lead
+// 0x81 holds punctuation. Half-width katakana and level-2 kanji are rare.
+fn shift_jis_score(bytes: &[u8]) -> i64 {
+    let mut score = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        match lead {
+            0x81..=0x9F | 0xE0..=0xFC => {
+                score += match lead {
+                    0x82 | 0x83 | 0x88..=0x98 => 3,
+                    0x81 => 1,
+                    _ => 0,
+                };
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    score
+}
+
+fn cp437_char(byte: u8) -> char {
+    const HIGH: [char; 128] = [
+        'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+        'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+        'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+        '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+        '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+        '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+        'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+        '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+    ];
+    if byte < 0x80 {
+        byte as char
+    } else {
+        HIGH[(byte - 0x80) as usize]
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Handling of entries whose destination path already exists.
+
+use crate::{
+    ffi, libarchive_entry_is_dir, libarchive_entry_mtime, EntryAction, EntryMetadata, Result,
+};
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The callback type of [`ConflictPolicy::Callback`].
+pub type ConflictCallback<'a> =
+    dyn Fn(&Path, &EntryMetadata) -> Result<ConflictResolution> + Send + Sync + 'a;
+
+/// What to do with an archive entry whose destination path already exists.
+///
+/// Directories extracted over existing directories are not conflicts: their
+/// content is merged and their metadata updated. Neither are entries whose
+/// path was already extracted earlier in the same run, such as a newer copy
+/// appended to a tar archive: the later entry replaces the earlier one.
+#[non_exhaustive]
+#[derive(Default)]
+pub enum ConflictPolicy<'a> {
+    /// Replace the existing file, libarchive's behavior.
+    #[default]
+    Overwrite,
+    /// Keep the existing file.
+    Skip,
+    /// Replace the existing file only if the entry has a more recent
+    /// modification time.
+    KeepNewer,
+    /// Fail the extraction with [`std::io::ErrorKind::AlreadyExists`].
+    Error,
+    /// Keep the existing file and extract the entry next to it, with the
+    /// given suffix appended to its name, e.g. `.new`. A file already at
+    /// that path is replaced. Suffixes containing a path separator fail the
+    /// extraction with [`std::io::ErrorKind::InvalidInput`].
+    RenameWithSuffix(String),
+    /// Let a callback decide, given the existing path and the incoming
+    /// entry's metadata. Returning an error fails the extraction.
+    Callback(Box<ConflictCallback<'a>>),
+}
+
+impl fmt::Debug for ConflictPolicy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::Overwrite => f.write_str("Overwrite"),
+            ConflictPolicy::Skip => f.write_str("Skip"),
+            ConflictPolicy::KeepNewer => f.write_str("KeepNewer"),
+            ConflictPolicy::Error => f.write_str("Error"),
+            ConflictPolicy::RenameWithSuffix(suffix) => {
+                f.debug_tuple("RenameWithSuffix").field(suffix).finish()
+            }
+            ConflictPolicy::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// The decision of a [`ConflictPolicy::Callback`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConflictResolution {
+    Overwrite,
+    Skip,
+    /// Extract the entry with this suffix appended to its name, which must
+    /// not contain a path separator.
+    RenameWithSuffix(String),
+}
+
+impl ConflictPolicy<'_> {
+    /// Decide how `entry` is extracted to `relative`, below `dest`, given the
+    /// paths already extracted in this run.
+    pub(crate) unsafe fn resolve(
+        &self,
+        dest: &Path,
+        relative: &Path,
+        entry: *mut ffi::archive_entry,
+        extracted: &HashSet<PathBuf>,
+    ) -> Result<EntryAction> {
+        let path = dest.join(relative);
+        let existing = match fs::symlink_metadata(&path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(EntryAction::Created),
+            Err(e) => return Err(e.into()),
+        };
+        if existing.is_dir() && libarchive_entry_is_dir(entry) {
+            return Ok(EntryAction::Merged);
+        }
+        if extracted.contains(relative) {
+            return Ok(EntryAction::Overwritten);
+        }
+
+        let resolution = match self {
+            ConflictPolicy::Overwrite => ConflictResolution::Overwrite,
+            ConflictPolicy::Skip => ConflictResolution::Skip,
+            ConflictPolicy::KeepNewer => {
+                let mtime = libarchive_entry_mtime(entry);
+                if existing.modified().is_ok_and(|existing| existing >= mtime) {
+                    ConflictResolution::Skip
+                } else {
+                    ConflictResolution::Overwrite
+                }
+            }
+            ConflictPolicy::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("path {} already exists", path.display()),
+                )
+                .into())
+            }
+            ConflictPolicy::RenameWithSuffix(suffix) => {
+                ConflictResolution::RenameWithSuffix(suffix.clone())
+            }
+            ConflictPolicy::Callback(callback) => {
+                callback(&path, &EntryMetadata::from_entry(entry))?
+            }
+        };
+
+        Ok(match resolution {
+            ConflictResolution::Overwrite => EntryAction::Overwritten,
+            ConflictResolution::Skip => EntryAction::Skipped,
+            ConflictResolution::RenameWithSuffix(suffix) => {
+                // The suffix must not move the entry to another directory.
+                if suffix.chars().any(std::path::is_separator) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid suffix {:?} for {}", suffix, path.display()),
+                    )
+                    .into());
+                }
+                let mut renamed = relative.as_os_str().to_owned();
+                renamed.push(suffix);
+                EntryAction::Renamed(renamed.into())
+            }
+        })
+    }
+}
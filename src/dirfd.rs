@@ -0,0 +1,216 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Extraction relative to an open directory handle. Every entry is created
+//! with the `*at` family of calls, walking one component at a time with
+//! `O_NOFOLLOW`, so a symlink swapped into the destination tree by another
+//! process cannot redirect writes outside of it.
+
+use crate::{EntryMetadata, ExtractSink, Ownership, Result};
+use std::{
+    ffi::{CString, OsStr},
+    fs, io,
+    os::{
+        fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+        unix::{ffi::OsStrExt, fs::PermissionsExt},
+    },
+    path::{Component, Path},
+};
+
+const DIR_FLAGS: libc::c_int =
+    libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+
+/// An [`ExtractSink`] creating entries below an open directory with
+/// `openat`, `mkdirat`, `symlinkat` and `linkat`.
+///
+/// No path is ever resolved through a symbolic link: each component is
+/// opened with `O_NOFOLLOW`, and extraction fails with an error if a
+/// directory along the way was replaced by a link. Existing files, links
+/// and empty directories in the way of an entry are replaced, as tar does.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::{fs::File, os::fd::AsFd};
+///
+/// let source = File::open("tree.tar.gz")?;
+/// let dest = File::open("/tmp/dest")?;
+/// let mut sink = DirFdSink::new(dest.as_fd()).ownership(Ownership::Preserve);
+///
+/// uncompress_archive_into(source, &mut sink)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct DirFdSink<'d> {
+    dir: BorrowedFd<'d>,
+    ownership: Ownership,
+}
+
+impl<'d> DirFdSink<'d> {
+    /// Create a sink extracting below the directory `dir`, ignoring
+    /// ownership.
+    pub fn new(dir: BorrowedFd<'d>) -> DirFdSink<'d> {
+        DirFdSink {
+            dir,
+            ownership: Ownership::Ignore,
+        }
+    }
+
+    /// Set whether ownership stored in the archive is applied.
+    pub fn ownership(mut self, ownership: Ownership) -> DirFdSink<'d> {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Open the parent directory of `path`, creating missing directories,
+    /// and return it along with the final component.
+    fn open_parent(&self, path: &Path) -> Result<(OwnedFd, CString)> {
+        let mut components = path.components().filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        });
+        let name = components
+            .next_back()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty entry path"))?;
+
+        let mut parent = self.dir.try_clone_to_owned()?;
+        for component in components {
+            let component = c_name(component)?;
+            parent = match open_dir(parent.as_raw_fd(), &component) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    mkdir(parent.as_raw_fd(), &component, 0o755)?;
+                    open_dir(parent.as_raw_fd(), &component)?
+                }
+                opened => opened?,
+            };
+        }
+        Ok((parent, c_name(name)?))
+    }
+}
+
+impl ExtractSink for DirFdSink<'_> {
+    type File<'a>
+        = fs::File
+    where
+        Self: 'a;
+
+    fn create_dir(&mut self, path: &Path, _: &EntryMetadata) -> Result<()> {
+        let (parent, name) = self.open_parent(path)?;
+        // Only ever created owner accessible; `set_metadata` applies the mode.
+        match mkdir(parent.as_raw_fd(), &name, 0o700) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                // Fails on anything but a directory, including a symlink to one.
+                if let Err(e) = open_dir(parent.as_raw_fd(), &name) {
+                    remove(parent.as_raw_fd(), &name).map_err(|_| e)?;
+                    mkdir(parent.as_raw_fd(), &name, 0o700)?;
+                }
+            }
+            created => created?,
+        }
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path, _: &EntryMetadata) -> Result<fs::File> {
+        let (parent, name) = self.open_parent(path)?;
+        remove(parent.as_raw_fd(), &name)?;
+        let fd = cvt(unsafe {
+            libc::openat(
+                parent.as_raw_fd(),
+                name.as_ptr(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                0o600 as libc::c_uint,
+            )
+        })?;
+        Ok(unsafe { fs::File::from_raw_fd(fd) })
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path, _: &EntryMetadata) -> Result<()> {
+        let (parent, name) = self.open_parent(path)?;
+        let target = c_name(target.as_os_str())?;
+        remove(parent.as_raw_fd(), &name)?;
+        cvt(unsafe { libc::symlinkat(target.as_ptr(), parent.as_raw_fd(), name.as_ptr()) })?;
+        Ok(())
+    }
+
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let (target_parent, target_name) = self.open_parent(target)?;
+        let (parent, name) = self.open_parent(path)?;
+        remove(parent.as_raw_fd(), &name)?;
+        // Without AT_SYMLINK_FOLLOW, a symlink target is linked itself.
+        cvt(unsafe {
+            libc::linkat(
+                target_parent.as_raw_fd(),
+                target_name.as_ptr(),
+                parent.as_raw_fd(),
+                name.as_ptr(),
+                0,
+            )
+        })?;
+        Ok(())
+    }
+
+    fn set_metadata(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        let (parent, name) = self.open_parent(path)?;
+        let fd = cvt(unsafe {
+            libc::openat(
+                parent.as_raw_fd(),
+                name.as_ptr(),
+                libc::O_RDONLY | libc::O_NOFOLLOW | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        })?;
+        let file = unsafe { fs::File::from_raw_fd(fd) };
+
+        // Ownership first, as changing it clears the setuid and setgid bits.
+        if let Ownership::Preserve = self.ownership {
+            std::os::unix::fs::fchown(&file, Some(metadata.uid as u32), Some(metadata.gid as u32))?;
+        }
+        if let Some(mtime) = metadata.mtime {
+            file.set_modified(mtime)?;
+        }
+        file.set_permissions(fs::Permissions::from_mode(metadata.mode))?;
+        Ok(())
+    }
+}
+
+fn c_name(name: &OsStr) -> Result<CString> {
+    CString::new(name.as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e).into())
+}
+
+fn cvt(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+fn open_dir(parent: RawFd, name: &CString) -> io::Result<OwnedFd> {
+    let fd = cvt(unsafe { libc::openat(parent, name.as_ptr(), DIR_FLAGS) })?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn mkdir(parent: RawFd, name: &CString, mode: libc::mode_t) -> io::Result<()> {
+    cvt(unsafe { libc::mkdirat(parent, name.as_ptr(), mode) }).map(drop)
+}
+
+/// Remove whatever is at `name` but a non-empty directory, so it can be
+/// created anew.
+fn remove(parent: RawFd, name: &CString) -> io::Result<()> {
+    let removed = cvt(unsafe { libc::unlinkat(parent, name.as_ptr(), 0) }).or_else(|e| {
+        match e.raw_os_error() {
+            Some(libc::EISDIR) | Some(libc::EPERM) => {
+                cvt(unsafe { libc::unlinkat(parent, name.as_ptr(), libc::AT_REMOVEDIR) })
+            }
+            _ => Err(e),
+        }
+    });
+    match removed {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
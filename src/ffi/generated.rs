@@ -15,8 +15,19 @@ pub(crate) const ARCHIVE_EXTRACT_TIME: u32 = 4;
 pub(crate) const ARCHIVE_EXTRACT_ACL: u32 = 32;
 pub(crate) const ARCHIVE_EXTRACT_FFLAGS: u32 = 64;
 pub(crate) const ARCHIVE_EXTRACT_XATTR: u32 = 128;
+pub(crate) const ARCHIVE_EXTRACT_SECURE_SYMLINKS: u32 = 256;
+pub(crate) const ARCHIVE_EXTRACT_SPARSE: u32 = 4096;
 pub(crate) const ARCHIVE_FORMAT_BASE_MASK: ::std::os::raw::c_int = 0xff0000;
 pub(crate) const ARCHIVE_FORMAT_MTREE: ::std::os::raw::c_int = 0x80000;
+pub(crate) const AE_IFREG: u32 = 32768;
+pub(crate) const AE_IFDIR: u32 = 16384;
+pub(crate) const AE_IFLNK: u32 = 40960;
+pub(crate) const AE_IFSOCK: u32 = 49152;
+pub(crate) const AE_IFCHR: u32 = 8192;
+pub(crate) const AE_IFBLK: u32 = 24576;
+pub(crate) const AE_IFIFO: u32 = 4096;
+pub(crate) const ARCHIVE_READ_FORMAT_ENCRYPTION_UNSUPPORTED: i32 = -2;
+pub(crate) const ARCHIVE_READ_FORMAT_ENCRYPTION_DONT_KNOW: i32 = -1;
 pub(crate) type __dev_t = ::std::os::raw::c_ulong;
 pub(crate) type __uid_t = ::std::os::raw::c_uint;
 pub(crate) type __gid_t = ::std::os::raw::c_uint;
@@ -67,6 +78,20 @@ pub(crate) type archive_close_callback = ::std::option::Option<
         _client_data: *mut ::std::os::raw::c_void,
     ) -> ::std::os::raw::c_int,
 >;
+pub(crate) type archive_write_callback = ::std::option::Option<
+    unsafe extern "C" fn(
+        arg1: *mut archive,
+        _client_data: *mut ::std::os::raw::c_void,
+        _buffer: *const ::std::os::raw::c_void,
+        _length: usize,
+    ) -> la_ssize_t,
+>;
+pub(crate) type archive_passphrase_callback = ::std::option::Option<
+    unsafe extern "C" fn(
+        arg1: *mut archive,
+        _client_data: *mut ::std::os::raw::c_void,
+    ) -> *const ::std::os::raw::c_char,
+>;
 extern "C" {
     pub(crate) fn archive_read_new() -> *mut archive;
 }
@@ -79,6 +104,11 @@ extern "C" {
 extern "C" {
     pub(crate) fn archive_read_support_format_raw(arg1: *mut archive) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_read_support_format_zip_streamable(
+        arg1: *mut archive,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub(crate) fn archive_read_set_seek_callback(
         arg1: *mut archive,
@@ -100,6 +130,13 @@ extern "C" {
         arg2: *mut *mut archive_entry,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_read_data(
+        arg1: *mut archive,
+        arg2: *mut ::std::os::raw::c_void,
+        arg3: usize,
+    ) -> la_ssize_t;
+}
 extern "C" {
     pub(crate) fn archive_read_data_block(
         a: *mut archive,
@@ -108,24 +145,85 @@ extern "C" {
         offset: *mut la_int64_t,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_read_set_options(
+        _a: *mut archive,
+        opts: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub(crate) fn archive_read_add_passphrase(
         arg1: *mut archive,
         arg2: *const ::std::os::raw::c_char,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_read_set_passphrase_callback(
+        arg1: *mut archive,
+        client_data: *mut ::std::os::raw::c_void,
+        arg2: archive_passphrase_callback,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub(crate) fn archive_read_close(arg1: *mut archive) -> ::std::os::raw::c_int;
 }
 extern "C" {
     pub(crate) fn archive_read_free(arg1: *mut archive) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_write_new() -> *mut archive;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_bytes_in_last_block(
+        arg1: *mut archive,
+        bytes_in_last_block: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_format_7zip(arg1: *mut archive) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_format_pax_restricted(
+        arg1: *mut archive,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_format_zip(arg1: *mut archive) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_options(
+        _a: *mut archive,
+        opts: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_set_passphrase(
+        _a: *mut archive,
+        p: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_open(
+        arg1: *mut archive,
+        arg2: *mut ::std::os::raw::c_void,
+        arg3: archive_open_callback,
+        arg4: archive_write_callback,
+        arg5: archive_close_callback,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub(crate) fn archive_write_header(
         arg1: *mut archive,
         arg2: *mut archive_entry,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_write_data(
+        arg1: *mut archive,
+        arg2: *const ::std::os::raw::c_void,
+        arg3: usize,
+    ) -> la_ssize_t;
+}
 extern "C" {
     pub(crate) fn archive_write_data_block(
         arg1: *mut archive,
@@ -157,6 +255,48 @@ extern "C" {
         arg1: *mut archive,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub(crate) fn archive_write_disk_set_group_lookup(
+        arg1: *mut archive,
+        arg2: *mut ::std::os::raw::c_void,
+        arg3: ::std::option::Option<
+            unsafe extern "C" fn(
+                arg1: *mut ::std::os::raw::c_void,
+                arg2: *const ::std::os::raw::c_char,
+                arg3: la_int64_t,
+            ) -> la_int64_t,
+        >,
+        arg4: ::std::option::Option<unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void)>,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_disk_set_user_lookup(
+        arg1: *mut archive,
+        arg2: *mut ::std::os::raw::c_void,
+        arg3: ::std::option::Option<
+            unsafe extern "C" fn(
+                arg1: *mut ::std::os::raw::c_void,
+                arg2: *const ::std::os::raw::c_char,
+                arg3: la_int64_t,
+            ) -> la_int64_t,
+        >,
+        arg4: ::std::option::Option<unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void)>,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_write_disk_gid(
+        arg1: *mut archive,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: la_int64_t,
+    ) -> la_int64_t;
+}
+extern "C" {
+    pub(crate) fn archive_write_disk_uid(
+        arg1: *mut archive,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: la_int64_t,
+    ) -> la_int64_t;
+}
 extern "C" {
     pub(crate) fn archive_errno(arg1: *mut archive) -> ::std::os::raw::c_int;
 }
@@ -174,9 +314,43 @@ extern "C" {
         ...
     );
 }
+extern "C" {
+    pub(crate) fn archive_entry_new() -> *mut archive_entry;
+}
 extern "C" {
     pub(crate) fn archive_entry_free(arg1: *mut archive_entry);
 }
+extern "C" {
+    pub(crate) fn archive_entry_set_filetype(
+        arg1: *mut archive_entry,
+        arg2: ::std::os::raw::c_uint,
+    );
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_mtime(
+        arg1: *mut archive_entry,
+        arg2: __time_t,
+        arg3: ::std::os::raw::c_long,
+    );
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_pathname_utf8(
+        arg1: *mut archive_entry,
+        arg2: *const ::std::os::raw::c_char,
+    );
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_perm(arg1: *mut archive_entry, arg2: __mode_t);
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_size(arg1: *mut archive_entry, arg2: la_int64_t);
+}
+extern "C" {
+    pub(crate) fn archive_read_has_encrypted_entries(arg1: *mut archive) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_entry_is_encrypted(arg1: *mut archive_entry) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub(crate) fn archive_entry_hardlink(arg1: *mut archive_entry)
         -> *const ::std::os::raw::c_char;
@@ -200,3 +374,54 @@ extern "C" {
 extern "C" {
     pub(crate) fn archive_entry_stat(arg1: *mut archive_entry) -> *const stat;
 }
+extern "C" {
+    pub(crate) fn archive_entry_mtime(arg1: *mut archive_entry) -> __time_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_filetype(arg1: *mut archive_entry) -> __mode_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_perm(arg1: *mut archive_entry) -> __mode_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_mtime_is_set(arg1: *mut archive_entry) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub(crate) fn archive_entry_symlink(arg1: *mut archive_entry) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub(crate) fn archive_entry_mtime_nsec(arg1: *mut archive_entry) -> ::std::os::raw::c_long;
+}
+extern "C" {
+    pub(crate) fn archive_entry_size(arg1: *mut archive_entry) -> la_int64_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_uid(arg1: *mut archive_entry) -> la_int64_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_uid(arg1: *mut archive_entry, arg2: la_int64_t);
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_gid(arg1: *mut archive_entry, arg2: la_int64_t);
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_uname(
+        arg1: *mut archive_entry,
+        arg2: *const ::std::os::raw::c_char,
+    );
+}
+extern "C" {
+    pub(crate) fn archive_entry_set_gname(
+        arg1: *mut archive_entry,
+        arg2: *const ::std::os::raw::c_char,
+    );
+}
+extern "C" {
+    pub(crate) fn archive_entry_gid(arg1: *mut archive_entry) -> la_int64_t;
+}
+extern "C" {
+    pub(crate) fn archive_entry_uname(arg1: *mut archive_entry) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub(crate) fn archive_entry_gname(arg1: *mut archive_entry) -> *const ::std::os::raw::c_char;
+}
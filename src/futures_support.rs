@@ -2,10 +2,12 @@
 
 use crate::{
     async_support::{
-        self, new_async_archive_iterator, AsyncArchiveIterator, AsyncEntryFilterCallbackFn,
-        BlockingExecutor,
+        self, new_async_archive_iterator, AsyncArchiveFileReader, AsyncArchiveIterator,
+        AsyncEntryFilterCallbackFn, AsyncEntryStream, BlockingExecutor,
     },
-    ArchivePassword, DecodeCallback, Ownership, Result,
+    iterator::IteratorOptions,
+    ArchivePassword, EntryMatcher, ExtractOptions, ExtractReport, Ownership, ReadOption,
+    ReadOptions, Result,
 };
 use async_trait::async_trait;
 use futures_io::{AsyncRead, AsyncSeek, AsyncWrite};
@@ -29,12 +31,10 @@ const FUTURES_BLOCKING_EXECUTOR: FuturesBlockingExecutor = FuturesBlockingExecut
 /// Async version of
 /// [`list_archive_files_with_encoding`](crate::
 /// list_archive_files_with_encoding).
-pub async fn list_archive_files_with_encoding<R>(
-    source: R,
-    decode: DecodeCallback,
-) -> Result<Vec<String>>
+pub async fn list_archive_files_with_encoding<R, D>(source: R, decode: D) -> Result<Vec<String>>
 where
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     async_support::list_archive_files_with_encoding(FUTURES_BLOCKING_EXECUTOR, source, decode).await
 }
@@ -47,15 +47,62 @@ where
     async_support::list_archive_files(FUTURES_BLOCKING_EXECUTOR, source).await
 }
 
+/// Async version of
+/// [`list_archive_files_bytes`](crate::list_archive_files_bytes).
+pub async fn list_archive_files_bytes<R>(source: R) -> Result<Vec<Vec<u8>>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::list_archive_files_bytes(FUTURES_BLOCKING_EXECUTOR, source).await
+}
+
+/// Async version of [`list_archive_files_os`](crate::list_archive_files_os).
+pub async fn list_archive_files_os<R>(source: R) -> Result<Vec<std::ffi::OsString>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::list_archive_files_os(FUTURES_BLOCKING_EXECUTOR, source).await
+}
+
+/// Async version of [`archive_encryption`](crate::archive_encryption).
+pub async fn archive_encryption<R>(source: R) -> Result<crate::ArchiveEncryption>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::archive_encryption(FUTURES_BLOCKING_EXECUTOR, source).await
+}
+
+/// Async version of [`list_zip_encryption`](crate::list_zip_encryption).
+pub async fn list_zip_encryption<R>(source: R) -> Result<Vec<crate::ZipEntryEncryption>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::list_zip_encryption(FUTURES_BLOCKING_EXECUTOR, source).await
+}
+
+/// Async version of
+/// [`list_archive_files_with_options`](crate::
+/// list_archive_files_with_options).
+pub async fn list_archive_files_with_options<R>(
+    source: R,
+    options: ReadOptions<'static>,
+) -> Result<Vec<String>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::list_archive_files_with_options(FUTURES_BLOCKING_EXECUTOR, source, options).await
+}
+
 /// Async version of
 /// [`list_archive_entries_with_encoding`](crate::
 /// list_archive_entries_with_encoding).
-pub async fn list_archive_entries_with_encoding<R>(
+pub async fn list_archive_entries_with_encoding<R, D>(
     source: R,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<Vec<crate::ArchiveEntryInfo>>
 where
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     async_support::list_archive_entries_with_encoding(FUTURES_BLOCKING_EXECUTOR, source, decode)
         .await
@@ -69,6 +116,20 @@ where
     async_support::list_archive_entries(FUTURES_BLOCKING_EXECUTOR, source).await
 }
 
+/// Async version of
+/// [`list_archive_entries_with_options`](crate::
+/// list_archive_entries_with_options).
+pub async fn list_archive_entries_with_options<R>(
+    source: R,
+    options: ReadOptions<'static>,
+) -> Result<Vec<crate::ArchiveEntryInfo>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::list_archive_entries_with_options(FUTURES_BLOCKING_EXECUTOR, source, options)
+        .await
+}
+
 /// Async version of [`uncompress_data`](crate::uncompress_data).
 pub async fn uncompress_data<R, W>(source: R, target: W) -> Result<usize>
 where
@@ -81,14 +142,15 @@ where
 /// Async version of
 /// [`uncompress_archive_with_encoding`](crate::
 /// uncompress_archive_with_encoding).
-pub async fn uncompress_archive_with_encoding<R>(
+pub async fn uncompress_archive_with_encoding<R, D>(
     source: R,
     dest: &Path,
     ownership: Ownership,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<()>
 where
     R: AsyncRead + AsyncSeek + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     async_support::uncompress_archive_with_encoding(
         FUTURES_BLOCKING_EXECUTOR,
@@ -108,18 +170,34 @@ where
     async_support::uncompress_archive(FUTURES_BLOCKING_EXECUTOR, source, dest, ownership).await
 }
 
+/// Async version of
+/// [`uncompress_archive_with_options`](crate::
+/// uncompress_archive_with_options).
+pub async fn uncompress_archive_with_options<R>(
+    source: R,
+    dest: &Path,
+    options: ExtractOptions<'static>,
+) -> Result<ExtractReport>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    async_support::uncompress_archive_with_options(FUTURES_BLOCKING_EXECUTOR, source, dest, options)
+        .await
+}
+
 /// Async version of
 /// [`uncompress_archive_file_with_encoding`](crate::
 /// uncompress_archive_file_with_encoding).
-pub async fn uncompress_archive_file_with_encoding<R, W>(
+pub async fn uncompress_archive_file_with_encoding<R, W, D>(
     source: R,
     target: W,
     path: &str,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<usize>
 where
     R: AsyncRead + AsyncSeek + Unpin,
     W: AsyncWrite + Unpin,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
     async_support::uncompress_archive_file_with_encoding(
         FUTURES_BLOCKING_EXECUTOR,
@@ -141,6 +219,51 @@ where
     async_support::uncompress_archive_file(FUTURES_BLOCKING_EXECUTOR, source, target, path).await
 }
 
+/// Async version of
+/// [`uncompress_archive_file_with_options`](crate::
+/// uncompress_archive_file_with_options).
+pub async fn uncompress_archive_file_with_options<R, W>(
+    source: R,
+    target: W,
+    path: &str,
+    options: ReadOptions<'static>,
+) -> Result<usize>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    async_support::uncompress_archive_file_with_options(
+        FUTURES_BLOCKING_EXECUTOR,
+        source,
+        target,
+        path,
+        options,
+    )
+    .await
+}
+
+/// Open the entry at `path` of an archive as an [`AsyncRead`], extracting it
+/// as the reader is consumed. See [`AsyncArchiveFileReader`].
+pub fn open_archive_file<R>(source: R, path: &str) -> AsyncArchiveFileReader
+where
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    async_support::open_archive_file(FUTURES_BLOCKING_EXECUTOR, source, path)
+}
+
+/// Like [`open_archive_file`], configuring the reader through
+/// [`ReadOptions`], e.g. to supply passwords.
+pub fn open_archive_file_with_options<R>(
+    source: R,
+    path: &str,
+    options: ReadOptions<'static>,
+) -> AsyncArchiveFileReader
+where
+    R: AsyncRead + AsyncSeek + Unpin + Send + 'static,
+{
+    async_support::open_archive_file_with_options(FUTURES_BLOCKING_EXECUTOR, source, path, options)
+}
+
 // ---------------------------------------------------------------------------
 // Async archive iterator
 // ---------------------------------------------------------------------------
@@ -155,9 +278,7 @@ where
 #[must_use]
 pub struct ArchiveIteratorBuilder<R> {
     source: R,
-    decoder: DecodeCallback,
-    filter: Option<Box<AsyncEntryFilterCallbackFn>>,
-    password: Option<ArchivePassword>,
+    options: IteratorOptions<AsyncEntryFilterCallbackFn>,
 }
 
 impl<R> ArchiveIteratorBuilder<R>
@@ -167,14 +288,15 @@ where
     pub fn new(source: R) -> ArchiveIteratorBuilder<R> {
         ArchiveIteratorBuilder {
             source,
-            decoder: crate::decode_utf8,
-            filter: None,
-            password: None,
+            options: IteratorOptions::default(),
         }
     }
 
-    pub fn decoder(mut self, decoder: DecodeCallback) -> ArchiveIteratorBuilder<R> {
-        self.decoder = decoder;
+    pub fn decoder<D>(mut self, decoder: D) -> ArchiveIteratorBuilder<R>
+    where
+        D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+    {
+        self.options = self.options.decoder(decoder);
         self
     }
 
@@ -182,21 +304,82 @@ where
     where
         F: Fn(&str, &crate::stat) -> bool + Send + Sync + 'static,
     {
-        self.filter = Some(Box::new(filter));
+        self.options = self.options.filter(Box::new(filter));
         self
     }
 
+    /// Add a password to decrypt encrypted entries. May be called multiple
+    /// times to try several candidates.
     pub fn with_password(mut self, password: ArchivePassword) -> ArchiveIteratorBuilder<R> {
-        self.password = Some(password);
+        self.options = self.options.password(password);
+        self
+    }
+
+    /// Enable libarchive's "raw" format handler. See
+    /// [`crate::ArchiveIteratorBuilder::raw_format`].
+    pub fn raw_format(mut self, enable: bool) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.raw_format(enable);
+        self
+    }
+
+    /// Accept entries from libarchive's "mtree" format handler (default).
+    /// See [`crate::ArchiveIteratorBuilder::mtree_format`].
+    pub fn mtree_format(mut self, enable: bool) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.mtree_format(enable);
+        self
+    }
+
+    /// Ask `callback` for a password when the ones given to
+    /// [`with_password`](Self::with_password) do not decrypt an entry. See
+    /// [`ReadOptions::passphrase_callback`].
+    pub fn passphrase_callback<F>(mut self, callback: F) -> ArchiveIteratorBuilder<R>
+    where
+        F: FnMut() -> Option<ArchivePassword> + Send + 'static,
+    {
+        self.options = self.options.passphrase_callback(callback);
+        self
+    }
+
+    /// Replace all reader settings at once, e.g. to share a [`ReadOptions`]
+    /// between the iterator and the one-shot functions.
+    pub fn read_options(mut self, read_options: ReadOptions<'static>) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.read_options(read_options);
+        self
+    }
+
+    /// Pass an option through to libarchive's reader. See
+    /// [`crate::ArchiveIteratorBuilder::read_option`].
+    pub fn read_option(mut self, option: ReadOption) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.read_option(option);
+        self
+    }
+
+    /// Detect the charset of legacy ZIP entry names. See
+    /// [`ReadOptions::detect_zip_charset`].
+    #[cfg(feature = "charset_detection")]
+    pub fn detect_zip_charset(
+        mut self,
+        detector: crate::ZipCharsetDetector,
+    ) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.detect_zip_charset(detector);
+        self
+    }
+
+    /// Only yield entries selected by `matcher`. See
+    /// [`ReadOptions::matcher`].
+    pub fn matcher(mut self, matcher: EntryMatcher) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.matcher(matcher);
         self
     }
 
     pub fn build(self) -> AsyncArchiveIterator {
-        new_async_archive_iterator::<FuturesBlockingExecutor, _>(
-            self.source,
-            self.decoder,
-            self.filter,
-            self.password,
-        )
+        new_async_archive_iterator::<FuturesBlockingExecutor, _>(self.source, self.options)
+    }
+
+    /// Build a stream yielding each entry with a reader over its content,
+    /// instead of the flat [`ArchiveContents`](crate::ArchiveContents) items
+    /// of [`build`](Self::build).
+    pub fn build_entries(self) -> AsyncEntryStream {
+        AsyncEntryStream::new(self.build())
     }
 }
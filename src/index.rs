@@ -0,0 +1,309 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Random access to single entries. ZIP archives are indexed from their
+//! central directory so an entry is read by seeking straight to its local
+//! header; every other format falls back to scanning the headers from the
+//! start on each lookup.
+
+use crate::{
+    error::archive_result, ffi, libarchive_entry_pathname, zip_preflight, Error, ReadAndSeek,
+    ReadOptions, Result, READER_BUFFER_SIZE,
+};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    io::{self, Read, Seek, SeekFrom},
+    os::raw::{c_int, c_void},
+};
+
+struct IndexEntry {
+    offset: u64,
+    method: u16,
+}
+
+/// Opens entries of an archive by name without extracting the rest.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::{fs::File, io::Read};
+///
+/// let source = File::open("tree.zip")?;
+/// let mut index = ArchiveIndex::new(source)?;
+///
+/// let mut leaf = String::new();
+/// index.open("tree/branch2/leaf")?.read_to_string(&mut leaf)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ArchiveIndex<R>
+where
+    R: Read + Seek,
+{
+    source: R,
+    options: ReadOptions<'static>,
+    entries: Option<HashMap<String, IndexEntry>>,
+}
+
+impl<R> ArchiveIndex<R>
+where
+    R: Read + Seek,
+{
+    /// Index `source`. Only ZIP archives are read up front; see
+    /// [`is_random_access`](Self::is_random_access).
+    pub fn new(source: R) -> Result<ArchiveIndex<R>> {
+        ArchiveIndex::with_options(source, ReadOptions::default())
+    }
+
+    /// Index `source`, using `options` to decode entry names and to
+    /// configure the libarchive reader of every [`open`](Self::open).
+    pub fn with_options(mut source: R, options: ReadOptions<'static>) -> Result<ArchiveIndex<R>> {
+        let options = options.resolve(&mut source).into_owned();
+        let central_directory = zip_preflight::central_directory(&mut source);
+
+        let entries = if central_directory.is_empty() {
+            None
+        } else {
+            let mut entries = HashMap::with_capacity(central_directory.len());
+            for entry in central_directory {
+                let name = match entry.unicode_path {
+                    Some(name) => name,
+                    None => match options.decode(&entry.name) {
+                        Ok(name) => name,
+                        Err(_) => continue,
+                    },
+                };
+                entries.insert(
+                    name,
+                    IndexEntry {
+                        offset: entry.offset,
+                        method: entry.method,
+                    },
+                );
+            }
+            Some(entries)
+        };
+
+        Ok(ArchiveIndex {
+            source,
+            options,
+            entries,
+        })
+    }
+
+    /// Whether entries are located through the ZIP central directory. When
+    /// `false`, each [`open`](Self::open) scans the archive from the start.
+    pub fn is_random_access(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// Open the entry called `name` for reading. Directories read as empty.
+    ///
+    /// Fails with [`std::io::ErrorKind::NotFound`] if there is no such
+    /// entry.
+    pub fn open(&mut self, name: &str) -> Result<EntryReader<'_>> {
+        let _utf8_guard = ffi::UTF8LocaleGuard::new();
+        match &self.entries {
+            Some(entries) => {
+                let entry = entries.get(name).ok_or_else(|| not_found(name))?;
+                if zip_preflight::is_unsupported_method(entry.method) {
+                    return Err(Error::UnsupportedZipCompression(vec![(
+                        name.to_string(),
+                        entry.method,
+                    )]));
+                }
+                self.source.seek(SeekFrom::Start(entry.offset))?;
+
+                let reader = unsafe { EntryReader::new(&mut self.source, &self.options, true)? };
+                let mut archive_entry = std::ptr::null_mut();
+                unsafe {
+                    archive_result(
+                        ffi::archive_read_next_header(reader.archive_reader, &mut archive_entry),
+                        reader.archive_reader,
+                    )?;
+                }
+                Ok(reader)
+            }
+            None => {
+                self.source.seek(SeekFrom::Start(0))?;
+
+                let reader = unsafe { EntryReader::new(&mut self.source, &self.options, false)? };
+                let mut archive_entry = std::ptr::null_mut();
+                loop {
+                    unsafe {
+                        match ffi::archive_read_next_header(
+                            reader.archive_reader,
+                            &mut archive_entry,
+                        ) {
+                            ffi::ARCHIVE_EOF => return Err(not_found(name)),
+                            value => archive_result(value, reader.archive_reader)?,
+                        }
+                    }
+
+                    let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
+                    let cstr = libarchive_entry_pathname(archive_entry)?;
+                    if self.options.decode(cstr.to_bytes())? == name {
+                        return Ok(reader);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Unwrap the underlying source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+fn not_found(name: &str) -> Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("path {} doesn't exist inside archive", name),
+    )
+    .into()
+}
+
+struct EntryPipe<'a> {
+    reader: &'a mut dyn ReadAndSeek,
+    buffer: Vec<u8>,
+}
+
+/// The content of a single entry, returned by [`ArchiveIndex::open`].
+pub struct EntryReader<'a> {
+    archive_reader: *mut ffi::archive,
+    _pipe: Box<EntryPipe<'a>>,
+}
+
+impl<'a> EntryReader<'a> {
+    /// Start a libarchive reader at the current position of `reader`. With
+    /// `zip_entry` set, only the streaming ZIP format is enabled, as the
+    /// reader sits on a local file header rather than the archive start;
+    /// otherwise formats such as 7z may seek in `reader`.
+    unsafe fn new(
+        reader: &'a mut dyn ReadAndSeek,
+        options: &ReadOptions,
+        zip_entry: bool,
+    ) -> Result<EntryReader<'a>> {
+        let archive_reader = ffi::archive_read_new();
+        if archive_reader.is_null() {
+            return Err(Error::NullArchive);
+        }
+        let mut pipe = Box::new(EntryPipe {
+            reader,
+            buffer: vec![0; READER_BUFFER_SIZE],
+        });
+        let client_data = std::ptr::addr_of_mut!(*pipe) as *mut c_void;
+        // Frees the archive on early returns below.
+        let entry_reader = EntryReader {
+            archive_reader,
+            _pipe: pipe,
+        };
+
+        if zip_entry {
+            archive_result(
+                ffi::archive_read_support_format_zip_streamable(archive_reader),
+                archive_reader,
+            )?;
+        } else {
+            archive_result(
+                ffi::archive_read_support_filter_all(archive_reader),
+                archive_reader,
+            )?;
+            archive_result(
+                ffi::archive_read_support_format_all(archive_reader),
+                archive_reader,
+            )?;
+            archive_result(
+                ffi::archive_read_set_seek_callback(
+                    archive_reader,
+                    Some(libarchive_entry_seek_callback),
+                ),
+                archive_reader,
+            )?;
+        }
+        options.apply(archive_reader)?;
+
+        archive_result(
+            ffi::archive_read_open(
+                archive_reader,
+                client_data,
+                None,
+                Some(libarchive_entry_read_callback),
+                None,
+            ),
+            archive_reader,
+        )?;
+
+        Ok(entry_reader)
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = unsafe {
+            ffi::archive_read_data(
+                self.archive_reader,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+            )
+        };
+        if read < 0 {
+            return Err(io::Error::other(Error::from(self.archive_reader)));
+        }
+        Ok(read as usize)
+    }
+}
+
+impl Drop for EntryReader<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::archive_read_free(self.archive_reader);
+        }
+    }
+}
+
+unsafe extern "C" fn libarchive_entry_read_callback(
+    archive: *mut ffi::archive,
+    client_data: *mut c_void,
+    buffer: *mut *const c_void,
+) -> ffi::la_ssize_t {
+    let pipe = (client_data as *mut EntryPipe).as_mut().unwrap();
+
+    *buffer = pipe.buffer.as_ptr() as *const c_void;
+
+    match pipe.reader.read(&mut pipe.buffer) {
+        Ok(size) => size as ffi::la_ssize_t,
+        Err(e) => {
+            let description = CString::new(e.to_string()).unwrap();
+
+            ffi::archive_set_error(archive, e.raw_os_error().unwrap_or(0), description.as_ptr());
+
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn libarchive_entry_seek_callback(
+    _: *mut ffi::archive,
+    client_data: *mut c_void,
+    offset: ffi::la_int64_t,
+    whence: c_int,
+) -> i64 {
+    let pipe = (client_data as *mut EntryPipe).as_mut().unwrap();
+    let whence = match whence {
+        0 => SeekFrom::Start(offset as u64),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => return -1,
+    };
+
+    match pipe.reader.seek(whence) {
+        Ok(offset) => offset as i64,
+        Err(_) => -1,
+    }
+}
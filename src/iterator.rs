@@ -11,9 +11,11 @@ use crate::stat;
 #[cfg(not(target_os = "windows"))]
 use libc::stat;
 
+#[cfg(feature = "charset_detection")]
+use crate::ZipCharsetDetector;
 use crate::{
-    error::archive_result, ffi, ffi::UTF8LocaleGuard, libarchive_entry_is_dir, DecodeCallback,
-    Error, Result, READER_BUFFER_SIZE,
+    error::archive_result, ffi, ffi::UTF8LocaleGuard, libarchive_entry_is_dir, EntryMatcher, Error,
+    ReadOption, ReadOptions, Result, READER_BUFFER_SIZE,
 };
 
 struct HeapReadSeekerPipe<R: Read + Seek> {
@@ -32,9 +34,14 @@ struct HeapReadSeekerPipe<R: Read + Seek> {
 pub enum ArchiveContents {
     /// Marks the start of an entry, either a file or a directory.
     StartOfEntry(String, stat),
-    /// A chunk of uncompressed data from the entry. Entries may have zero or
-    /// more chunks.
-    DataChunk(Vec<u8>),
+    /// A chunk of uncompressed data from the entry, and its offset within
+    /// the entry. Entries may have zero or more chunks. The chunks of sparse
+    /// entries are not contiguous: the gaps between them are holes reading
+    /// as zeros. When an entry ends with a hole, a last, empty chunk is
+    /// yielded at the end of the entry, so that the offset and length of the
+    /// last chunk always add up to the entry's length, which the size in its
+    /// `stat` may not match.
+    DataChunk(Vec<u8>, u64),
     /// Marks the end of the entry that was started by the previous
     /// StartOfEntry.
     EndOfEntry,
@@ -54,6 +61,7 @@ pub type EntryFilterCallbackFn = dyn Fn(&str, &stat) -> bool;
 /// Construct with [`ArchivePassword::new`] — it fails if the supplied string
 /// contains an interior NUL byte, which cannot be passed through to
 /// libarchive's C API.
+#[derive(Clone)]
 pub struct ArchivePassword(CString);
 
 impl ArchivePassword {
@@ -74,9 +82,12 @@ pub struct ArchiveIterator<R: Read + Seek> {
     archive_entry: *mut ffi::archive_entry,
     archive_reader: *mut ffi::archive,
 
-    decode: DecodeCallback,
+    read_options: ReadOptions<'static>,
     in_file: bool,
     current_is_dir: bool,
+    // End of the data read so far from the current entry.
+    data_end: u64,
+    entry_ended: bool,
     closed: bool,
     error: bool,
     mtree_format: bool,
@@ -112,11 +123,19 @@ impl<R: Read + Seek> Iterator for ArchiveIterator<R> {
                             continue;
                         }
                     }
+                    match unsafe { self.read_options.selects(self.archive_entry) } {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(e) => {
+                            self.error = true;
+                            break Some(ArchiveContents::Err(e));
+                        }
+                    }
 
                     self.in_file = true;
                     break Some(next);
                 }
-                ArchiveContents::DataChunk(_) => {
+                ArchiveContents::DataChunk(..) => {
                     debug_assert!(self.in_file);
                     break Some(next);
                 }
@@ -135,6 +154,22 @@ impl<R: Read + Seek> Iterator for ArchiveIterator<R> {
 }
 
 impl<R: Read + Seek> ArchiveIterator<R> {
+    /// The pathname bytes of the most recently started entry, exactly as
+    /// stored in the archive.
+    ///
+    /// Useful together with a lossy [`decoder`](ArchiveIteratorBuilder::decoder)
+    /// when names may not be valid in any charset, e.g. arbitrary Unix paths.
+    pub fn entry_pathname_bytes(&self) -> Option<&[u8]> {
+        if self.archive_entry.is_null() {
+            return None;
+        }
+        let pathname = unsafe { ffi::archive_entry_pathname(self.archive_entry) };
+        if pathname.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(pathname) }.to_bytes())
+    }
+
     pub fn next_header(&mut self) -> Option<ArchiveContents> {
         debug_assert!(!self.closed);
 
@@ -151,6 +186,14 @@ impl<R: Read + Seek> ArchiveIterator<R> {
                         return None;
                     }
                 }
+                match unsafe { self.read_options.selects(self.archive_entry) } {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(e) => {
+                        self.error = true;
+                        return Some(ArchiveContents::Err(e));
+                    }
+                }
 
                 self.in_file = true;
                 Some(next)
@@ -171,21 +214,25 @@ impl<R: Read + Seek> Drop for ArchiveIterator<R> {
 }
 
 impl<R: Read + Seek> ArchiveIterator<R> {
-    fn new(
+    pub(crate) fn new(
         mut source: R,
-        decode: DecodeCallback,
-        filter: Option<Box<EntryFilterCallbackFn>>,
-        password: Option<ArchivePassword>,
-        raw_format: bool,
-        mtree_format: bool,
+        options: IteratorOptions<EntryFilterCallbackFn>,
     ) -> Result<ArchiveIterator<R>>
     where
         R: Read + Seek,
     {
+        let IteratorOptions {
+            filter,
+            raw_format,
+            mtree_format,
+            read_options,
+        } = options;
         let utf8_guard = ffi::UTF8LocaleGuard::new();
         // libarchive only sniffs the format from offset 0.
         source.seek(SeekFrom::Start(0))?;
         crate::zip_preflight::reject_unsupported_zip_methods(&mut source)?;
+        let resolved = read_options.resolve(&mut source);
+        let read_options = &*resolved;
         let reader = source;
         let buffer = [0; READER_BUFFER_SIZE];
         let mut pipe = Box::new(HeapReadSeekerPipe { reader, buffer });
@@ -195,13 +242,6 @@ impl<R: Read + Seek> ArchiveIterator<R> {
             let archive_reader = ffi::archive_read_new();
 
             let res = (|| {
-                if let Some(password) = password {
-                    archive_result(
-                        ffi::archive_read_add_passphrase(archive_reader, password.as_ptr()),
-                        archive_reader,
-                    )?;
-                }
-
                 archive_result(
                     ffi::archive_read_support_filter_all(archive_reader),
                     archive_reader,
@@ -230,6 +270,7 @@ impl<R: Read + Seek> ArchiveIterator<R> {
                     ffi::archive_read_support_format_all(archive_reader),
                     archive_reader,
                 )?;
+                read_options.apply(archive_reader)?;
 
                 archive_result(
                     ffi::archive_read_open(
@@ -249,9 +290,11 @@ impl<R: Read + Seek> ArchiveIterator<R> {
                 archive_entry,
                 archive_reader,
 
-                decode,
+                read_options: read_options.clone(),
                 in_file: false,
                 current_is_dir: false,
+                data_end: 0,
+                entry_ended: false,
                 closed: false,
                 error: false,
                 mtree_format,
@@ -291,7 +334,7 @@ impl<R: Read + Seek> ArchiveIterator<R> {
     ///             name = s;
     ///             println!("header reports {} bytes for {}", stat.st_size, name);
     ///         }
-    ///         ArchiveContents::DataChunk(v) => size += v.len(),
+    ///         ArchiveContents::DataChunk(v, _) => size += v.len(),
     ///         ArchiveContents::EndOfEntry => {
     ///             println!("Entry {} was {} bytes", name, size);
     ///             size = 0;
@@ -306,11 +349,12 @@ impl<R: Read + Seek> ArchiveIterator<R> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn from_read_with_encoding(source: R, decode: DecodeCallback) -> Result<ArchiveIterator<R>>
+    pub fn from_read_with_encoding<D>(source: R, decode: D) -> Result<ArchiveIterator<R>>
     where
         R: Read + Seek,
+        D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
     {
-        Self::new(source, decode, None, None, false, true)
+        Self::new(source, IteratorOptions::default().decoder(decode))
     }
 
     /// Iterate over the contents of an archive, streaming the contents of each
@@ -330,7 +374,7 @@ impl<R: Read + Seek> ArchiveIterator<R> {
     /// for content in &mut iter {
     ///     match content {
     ///         ArchiveContents::StartOfEntry(s, _) => name = s,
-    ///         ArchiveContents::DataChunk(v) => size += v.len(),
+    ///         ArchiveContents::DataChunk(v, _) => size += v.len(),
     ///         ArchiveContents::EndOfEntry => {
     ///             println!("Entry {} was {} bytes", name, size);
     ///             size = 0;
@@ -349,7 +393,7 @@ impl<R: Read + Seek> ArchiveIterator<R> {
     where
         R: Read + Seek,
     {
-        Self::new(source, crate::decode_utf8, None, None, false, true)
+        Self::new(source, IteratorOptions::default())
     }
 
     /// Close the iterator, freeing up the associated resources.
@@ -390,12 +434,14 @@ impl<R: Read + Seek> ArchiveIterator<R> {
                 }
                 let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
                 let cstr = CStr::from_ptr(ffi::archive_entry_pathname(self.archive_entry));
-                let file_name = match (self.decode)(cstr.to_bytes()) {
+                let file_name = match self.read_options.decode(cstr.to_bytes()) {
                     Ok(f) => f,
                     Err(e) => return ArchiveContents::Err(e),
                 };
                 let stat = *ffi::archive_entry_stat(self.archive_entry);
                 self.current_is_dir = libarchive_entry_is_dir(self.archive_entry);
+                self.data_end = 0;
+                self.entry_ended = false;
                 ArchiveContents::StartOfEntry(file_name, stat)
             }
             _ => ArchiveContents::Err(Error::from(self.archive_reader)),
@@ -403,7 +449,7 @@ impl<R: Read + Seek> ArchiveIterator<R> {
     }
 
     unsafe fn next_data_chunk(&mut self) -> ArchiveContents {
-        if self.current_is_dir {
+        if self.current_is_dir || self.entry_ended {
             return ArchiveContents::EndOfEntry;
         }
 
@@ -414,8 +460,18 @@ impl<R: Read + Seek> ArchiveIterator<R> {
 
         match ffi::archive_read_data_block(self.archive_reader, &mut buffer, &mut size, &mut offset)
         {
-            ffi::ARCHIVE_EOF => ArchiveContents::EndOfEntry,
+            ffi::ARCHIVE_EOF => {
+                self.entry_ended = true;
+                // The offset is now the end of the entry, past any final hole.
+                let end = offset.max(0) as u64;
+                if end > self.data_end {
+                    ArchiveContents::DataChunk(Vec::new(), end)
+                } else {
+                    ArchiveContents::EndOfEntry
+                }
+            }
             ffi::ARCHIVE_OK | ffi::ARCHIVE_WARN => {
+                self.data_end = offset.max(0) as u64 + size as u64;
                 if size > 0 {
                     // fixes: (as buffer is null then) unsafe precondition(s) violated:
                     // slice::from_raw_parts requires the pointer to be aligned and non-null, and
@@ -425,10 +481,10 @@ impl<R: Read + Seek> ArchiveIterator<R> {
                     if let Err(e) = write {
                         ArchiveContents::Err(e.into())
                     } else {
-                        ArchiveContents::DataChunk(target)
+                        ArchiveContents::DataChunk(target, offset.max(0) as u64)
                     }
                 } else {
-                    ArchiveContents::DataChunk(target)
+                    ArchiveContents::DataChunk(target, offset.max(0) as u64)
                 }
             }
             _ => ArchiveContents::Err(Error::from(self.archive_reader)),
@@ -495,17 +551,93 @@ unsafe extern "C" fn libarchive_heap_seekableread_callback<R: Read + Seek>(
     }
 }
 
+/// Settings of an archive iterator, shared by [`ArchiveIteratorBuilder`] and
+/// the async builders so each option is handled in one place. `F` is the
+/// filter type, which must be `Send + Sync` for the async builders.
+pub(crate) struct IteratorOptions<F: ?Sized> {
+    pub(crate) filter: Option<Box<F>>,
+    pub(crate) raw_format: bool,
+    pub(crate) mtree_format: bool,
+    pub(crate) read_options: ReadOptions<'static>,
+}
+
+impl<F: ?Sized> Default for IteratorOptions<F> {
+    fn default() -> Self {
+        IteratorOptions {
+            filter: None,
+            raw_format: false,
+            mtree_format: true,
+            read_options: ReadOptions::default(),
+        }
+    }
+}
+
+impl<F: ?Sized> IteratorOptions<F> {
+    pub(crate) fn decoder<D>(mut self, decoder: D) -> Self
+    where
+        D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+    {
+        self.read_options = self.read_options.decoder(decoder);
+        self
+    }
+
+    pub(crate) fn filter(mut self, filter: Box<F>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub(crate) fn password(mut self, password: ArchivePassword) -> Self {
+        self.read_options = self.read_options.password(password);
+        self
+    }
+
+    pub(crate) fn raw_format(mut self, enable: bool) -> Self {
+        self.raw_format = enable;
+        self
+    }
+
+    pub(crate) fn mtree_format(mut self, enable: bool) -> Self {
+        self.mtree_format = enable;
+        self
+    }
+
+    pub(crate) fn passphrase_callback<C>(mut self, callback: C) -> Self
+    where
+        C: FnMut() -> Option<ArchivePassword> + Send + 'static,
+    {
+        self.read_options = self.read_options.passphrase_callback(callback);
+        self
+    }
+
+    pub(crate) fn read_options(mut self, read_options: ReadOptions<'static>) -> Self {
+        self.read_options = read_options;
+        self
+    }
+
+    pub(crate) fn read_option(mut self, option: ReadOption) -> Self {
+        self.read_options = self.read_options.option(option);
+        self
+    }
+
+    #[cfg(feature = "charset_detection")]
+    pub(crate) fn detect_zip_charset(mut self, detector: ZipCharsetDetector) -> Self {
+        self.read_options = self.read_options.detect_zip_charset(detector);
+        self
+    }
+
+    pub(crate) fn matcher(mut self, matcher: EntryMatcher) -> Self {
+        self.read_options = self.read_options.matcher(matcher);
+        self
+    }
+}
+
 #[must_use]
 pub struct ArchiveIteratorBuilder<R>
 where
     R: Read + Seek,
 {
     source: R,
-    decoder: DecodeCallback,
-    filter: Option<Box<EntryFilterCallbackFn>>,
-    password: Option<ArchivePassword>,
-    raw_format: bool,
-    mtree_format: bool,
+    options: IteratorOptions<EntryFilterCallbackFn>,
 }
 
 /// A builder to generate an archive iterator over the contents of an
@@ -542,18 +674,19 @@ where
     pub fn new(source: R) -> ArchiveIteratorBuilder<R> {
         ArchiveIteratorBuilder {
             source,
-            decoder: crate::decode_utf8,
-            filter: None,
-            password: None,
-            raw_format: false,
-            mtree_format: true,
+            options: IteratorOptions::default(),
         }
     }
 
     /// Use a custom decoder to decode filenames of archive entries.
     /// By default an UTF-8 decoder (`decode_utf8`) is used.
-    pub fn decoder(mut self, decoder: DecodeCallback) -> ArchiveIteratorBuilder<R> {
-        self.decoder = decoder;
+    ///
+    /// The decoder may capture state, e.g. an encoding picked at runtime.
+    pub fn decoder<D>(mut self, decoder: D) -> ArchiveIteratorBuilder<R>
+    where
+        D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+    {
+        self.options = self.options.decoder(decoder);
         self
     }
 
@@ -563,13 +696,16 @@ where
     where
         F: Fn(&str, &stat) -> bool + 'static,
     {
-        self.filter = Some(Box::new(filter));
+        self.options = self.options.filter(Box::new(filter));
         self
     }
 
     /// Set a custom password to decode content of archive entries.
+    ///
+    /// May be called multiple times; libarchive tries each password in turn
+    /// until one decrypts the entry.
     pub fn with_password(mut self, password: ArchivePassword) -> ArchiveIteratorBuilder<R> {
-        self.password = Some(password);
+        self.options = self.options.password(password);
         self
     }
 
@@ -580,7 +716,7 @@ where
     /// archive. Enable it only when you intentionally want to iterate over
     /// arbitrary non-archive streams (e.g. a standalone gzip file).
     pub fn raw_format(mut self, enable: bool) -> ArchiveIteratorBuilder<R> {
-        self.raw_format = enable;
+        self.options = self.options.raw_format(enable);
         self
     }
 
@@ -590,19 +726,53 @@ where
     /// text (a plain gunzip'd text file is enough); pass `false` to
     /// reject those matches and error out instead.
     pub fn mtree_format(mut self, enable: bool) -> ArchiveIteratorBuilder<R> {
-        self.mtree_format = enable;
+        self.options = self.options.mtree_format(enable);
+        self
+    }
+
+    /// Ask `callback` for a password when the ones given to
+    /// [`with_password`](Self::with_password) do not decrypt an entry. See
+    /// [`ReadOptions::passphrase_callback`].
+    pub fn passphrase_callback<F>(mut self, callback: F) -> ArchiveIteratorBuilder<R>
+    where
+        F: FnMut() -> Option<ArchivePassword> + Send + 'static,
+    {
+        self.options = self.options.passphrase_callback(callback);
+        self
+    }
+
+    /// Replace all reader settings at once, e.g. to share a [`ReadOptions`]
+    /// between the iterator and the one-shot functions.
+    pub fn read_options(mut self, read_options: ReadOptions<'static>) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.read_options(read_options);
+        self
+    }
+
+    /// Pass an option through to libarchive's reader, e.g.
+    /// [`ReadOption::HdrCharset`]. May be called multiple times; building
+    /// fails if libarchive rejects an option.
+    pub fn read_option(mut self, option: ReadOption) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.read_option(option);
+        self
+    }
+
+    /// Detect the charset of legacy ZIP entry names. See
+    /// [`ReadOptions::detect_zip_charset`].
+    #[cfg(feature = "charset_detection")]
+    pub fn detect_zip_charset(mut self, detector: ZipCharsetDetector) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.detect_zip_charset(detector);
+        self
+    }
+
+    /// Only yield entries selected by `matcher`. See
+    /// [`ReadOptions::matcher`].
+    pub fn matcher(mut self, matcher: EntryMatcher) -> ArchiveIteratorBuilder<R> {
+        self.options = self.options.matcher(matcher);
         self
     }
 
     /// Finish the builder and generate the configured `ArchiveIterator`.
     pub fn build(self) -> Result<ArchiveIterator<R>> {
-        ArchiveIterator::new(
-            self.source,
-            self.decoder,
-            self.filter,
-            self.password,
-            self.raw_format,
-            self.mtree_format,
-        )
+        ArchiveIterator::new(self.source, self.options)
     }
 }
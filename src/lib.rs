@@ -65,29 +65,68 @@
 
 #[cfg(feature = "async_support")]
 pub mod async_support;
+#[cfg(feature = "charset_detection")]
+mod charset;
+mod conflict;
+#[cfg(unix)]
+mod dirfd;
 mod error;
 mod ffi;
 #[cfg(feature = "futures_support")]
 pub mod futures_support;
+mod index;
 mod iterator;
+mod links;
+mod matcher;
+mod memory;
+mod options;
+mod owner;
+mod report;
+mod sink;
 #[cfg(feature = "tokio_support")]
 pub mod tokio_support;
+mod writer;
 mod zip_preflight;
 
+#[cfg(feature = "charset_detection")]
+pub use charset::{Charset, ZipCharsetDetector};
+pub use conflict::{ConflictCallback, ConflictPolicy, ConflictResolution};
+#[cfg(unix)]
+pub use dirfd::DirFdSink;
 use error::{archive_result, archive_result_strict};
 pub use error::{Error, Result};
+pub use index::{ArchiveIndex, EntryReader};
 use io::{Seek, SeekFrom};
 pub use iterator::{ArchiveContents, ArchiveIterator, ArchiveIteratorBuilder, ArchivePassword};
+use links::ExtractedSymlinks;
+pub use links::LinkPolicy;
+pub use matcher::EntryMatcher;
+pub use memory::{MemoryNode, MemoryTree};
+pub use options::{ExtractOptions, ReadOption, ReadOptions, SpecialFilePolicy};
+pub use owner::{IdRange, OwnerCallback, OwnerLookup, OwnerMapping};
+pub use report::{EntryAction, ExtractReport, ExtractedEntry};
+pub use sink::{DiskSink, EntryMetadata, ExtractSink};
 use std::{
-    ffi::{CStr, CString},
+    collections::{HashMap, HashSet},
+    ffi::{CStr, CString, OsString},
     io::{self, Read, Write},
     os::raw::{c_int, c_void},
-    path::{Component, Path},
+    path::{Component, Path, PathBuf},
     slice,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+pub use writer::{ArchiveFormat, ArchiveWriter, ArchiveWriterBuilder, EncryptionMethod};
+pub use zip_preflight::{ZipEncryption, ZipEntryEncryption};
 
 const READER_BUFFER_SIZE: usize = 16384;
 
+/// Flags of libarchive's disk writer used for every extraction.
+const DISK_WRITER_FLAGS: u32 = ffi::ARCHIVE_EXTRACT_TIME
+    | ffi::ARCHIVE_EXTRACT_PERM
+    | ffi::ARCHIVE_EXTRACT_ACL
+    | ffi::ARCHIVE_EXTRACT_FFLAGS
+    | ffi::ARCHIVE_EXTRACT_XATTR;
+
 /// Re-export of [`libc::stat`] so `crate::stat` resolves uniformly across
 /// platforms — Windows has its own layout declared below.
 #[cfg(not(target_os = "windows"))]
@@ -126,6 +165,25 @@ pub struct stat {
 pub struct ArchiveEntryInfo {
     pub path: String,
     pub size: u64,
+    /// Whether the entry data or metadata is encrypted, so reading it needs
+    /// a password.
+    pub is_encrypted: bool,
+}
+
+/// Whether an archive contains encrypted entries, as reported by
+/// [`archive_encryption`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveEncryption {
+    /// At least one entry is encrypted.
+    Encrypted,
+    /// No entry is encrypted.
+    NotEncrypted,
+    /// The format supports encryption, but libarchive cannot tell whether
+    /// this archive uses it.
+    Unknown,
+    /// The format has no notion of encryption, e.g. tar.
+    Unsupported,
 }
 
 /// Determine the ownership behavior when unpacking the archive.
@@ -151,7 +209,9 @@ struct SeekableReaderPipe<'a> {
     buffer: &'a mut [u8],
 }
 
-pub type DecodeCallback = fn(&[u8]) -> Result<String>;
+/// Decoder turning the raw pathname bytes stored in an archive into a
+/// `String`. See [`ReadOptions::decoder`].
+pub type DecodeCallback = dyn Fn(&[u8]) -> Result<String> + Send + Sync;
 
 pub(crate) fn decode_utf8(bytes: &[u8]) -> Result<String> {
     Ok(std::str::from_utf8(bytes)?.to_owned())
@@ -172,11 +232,38 @@ pub(crate) fn decode_utf8(bytes: &[u8]) -> Result<String> {
 /// # Ok(())
 /// # }
 /// ```
-pub fn list_archive_files_with_encoding<R>(source: R, decode: DecodeCallback) -> Result<Vec<String>>
+pub fn list_archive_files_with_encoding<R, D>(source: R, decode: D) -> Result<Vec<String>>
 where
     R: Read + Seek,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
 {
-    Ok(list_archive_entries_with_encoding(source, decode)?
+    list_archive_files_with_options(source, ReadOptions::new().decoder(decode))
+}
+
+/// Get all files in a archive using `source` as a reader and `options` to
+/// configure the libarchive reader.
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.zip")?;
+/// let options = ReadOptions::new().option(ReadOption::ZipIgnoreCrc32);
+///
+/// let file_list = list_archive_files_with_options(&mut source, options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_archive_files_with_options<R>(
+    source: R,
+    options: ReadOptions<'_>,
+) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+{
+    Ok(list_archive_entries_with_options(source, options)?
         .into_iter()
         .map(|e| e.path)
         .collect())
@@ -200,7 +287,153 @@ pub fn list_archive_files<R>(source: R) -> Result<Vec<String>>
 where
     R: Read + Seek,
 {
-    list_archive_files_with_encoding(source, decode_utf8)
+    list_archive_files_with_options(source, ReadOptions::default())
+}
+
+/// Get all files in a archive using `source` as a reader, as the pathname
+/// bytes stored in the archive.
+///
+/// Unlike [`list_archive_files`] no decoding takes place, so names that are
+/// not valid UTF-8 are listed instead of failing with [`Error::Utf`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar")?;
+///
+/// let file_list = list_archive_files_bytes(&mut source)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_archive_files_bytes<R>(source: R) -> Result<Vec<Vec<u8>>>
+where
+    R: Read + Seek,
+{
+    list_entries_with(source, &ReadOptions::default(), |_, entry| {
+        Ok(libarchive_entry_pathname(entry)?.to_bytes().to_vec())
+    })
+}
+
+/// Get all files in a archive using `source` as a reader, as [`OsString`]s
+/// built from the pathname bytes stored in the archive.
+///
+/// On Unix this is lossless, see [`list_archive_files_bytes`]. Elsewhere
+/// names are converted from UTF-8, replacing invalid sequences.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar")?;
+///
+/// let file_list = list_archive_files_os(&mut source)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_archive_files_os<R>(source: R) -> Result<Vec<OsString>>
+where
+    R: Read + Seek,
+{
+    Ok(list_archive_files_bytes(source)?
+        .into_iter()
+        .map(os_string_from_bytes)
+        .collect())
+}
+
+/// Find out whether an archive contains encrypted entries, reading only the
+/// entry headers, e.g. to decide whether to prompt for a password.
+///
+/// Wraps `archive_read_has_encrypted_entries(3)`. For per-entry details see
+/// [`ArchiveEntryInfo::is_encrypted`] and, for ZIP archives,
+/// [`list_zip_encryption`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.zip")?;
+///
+/// if archive_encryption(&mut source)? == ArchiveEncryption::Encrypted {
+///     println!("a password is needed");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn archive_encryption<R>(source: R) -> Result<ArchiveEncryption>
+where
+    R: Read + Seek,
+{
+    let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    run_with_archive(
+        DISK_WRITER_FLAGS,
+        &ReadOptions::default(),
+        source,
+        |archive_reader, _, mut entry| unsafe {
+            loop {
+                match ffi::archive_read_next_header(archive_reader, &mut entry) {
+                    ffi::ARCHIVE_EOF => break,
+                    value => archive_result(value, archive_reader)?,
+                }
+                if ffi::archive_entry_is_encrypted(entry) != 0 {
+                    return Ok(ArchiveEncryption::Encrypted);
+                }
+            }
+            Ok(
+                match ffi::archive_read_has_encrypted_entries(archive_reader) {
+                    0 => ArchiveEncryption::NotEncrypted,
+                    ffi::ARCHIVE_READ_FORMAT_ENCRYPTION_UNSUPPORTED => {
+                        ArchiveEncryption::Unsupported
+                    }
+                    ffi::ARCHIVE_READ_FORMAT_ENCRYPTION_DONT_KNOW => ArchiveEncryption::Unknown,
+                    _ => ArchiveEncryption::Encrypted,
+                },
+            )
+        },
+    )
+}
+
+/// Report the encryption scheme of every entry in a ZIP archive, read from
+/// its central directory without decrypting anything.
+///
+/// Inputs that are not ZIP archives yield an empty list. Entry names are
+/// decoded as UTF-8, replacing invalid sequences.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.zip")?;
+///
+/// for entry in list_zip_encryption(&mut source)? {
+///     println!("{}: {:?}", entry.path, entry.encryption);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_zip_encryption<R>(mut source: R) -> Result<Vec<ZipEntryEncryption>>
+where
+    R: Read + Seek,
+{
+    Ok(zip_preflight::central_directory(&mut source)
+        .into_iter()
+        .map(|entry| ZipEntryEncryption {
+            path: String::from_utf8_lossy(&entry.name).into_owned(),
+            encryption: entry.encryption,
+        })
+        .collect())
 }
 
 /// Get entry metadata (path and uncompressed size) for every entry in an
@@ -224,16 +457,72 @@ where
 /// # Ok(())
 /// # }
 /// ```
-pub fn list_archive_entries_with_encoding<R>(
+pub fn list_archive_entries_with_encoding<R, D>(
     source: R,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<Vec<ArchiveEntryInfo>>
+where
+    R: Read + Seek,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+{
+    list_entries(source, &ReadOptions::new().decoder(decode))
+}
+
+/// Get entry metadata (path and uncompressed size) for every entry in an
+/// archive, using `options` to configure the libarchive reader.
+///
+/// See [`ArchiveEntryInfo`] for caveats on `size` reporting across formats.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("concatenated.tar")?;
+/// let options = ReadOptions::new().option(ReadOption::TarReadConcatenatedArchives);
+///
+/// for entry in list_archive_entries_with_options(&mut source, options)? {
+///     println!("{}: {} bytes", entry.path, entry.size);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_archive_entries_with_options<R>(
+    source: R,
+    options: ReadOptions<'_>,
+) -> Result<Vec<ArchiveEntryInfo>>
+where
+    R: Read + Seek,
+{
+    list_entries(source, &options)
+}
+
+fn list_entries<R>(source: R, options: &ReadOptions) -> Result<Vec<ArchiveEntryInfo>>
 where
     R: Read + Seek,
+{
+    list_entries_with(source, options, |options, entry| {
+        Ok(ArchiveEntryInfo {
+            path: options.decode(libarchive_entry_pathname(entry)?.to_bytes())?,
+            size: libarchive_entry_size(entry),
+            is_encrypted: unsafe { ffi::archive_entry_is_encrypted(entry) } != 0,
+        })
+    })
+}
+
+fn list_entries_with<R, T, F>(mut source: R, options: &ReadOptions, mut f: F) -> Result<Vec<T>>
+where
+    R: Read + Seek,
+    F: FnMut(&ReadOptions, *mut ffi::archive_entry) -> Result<T>,
 {
     let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    let resolved = options.resolve(&mut source);
+    let options = &*resolved;
     run_with_archive(
-        Ownership::Ignore,
+        DISK_WRITER_FLAGS,
+        options,
         source,
         |archive_reader, _, mut entry| unsafe {
             let mut entries = Vec::new();
@@ -244,10 +533,9 @@ where
                 }
 
                 let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
-                let cstr = libarchive_entry_pathname(entry)?;
-                let path = decode(cstr.to_bytes())?;
-                let size = libarchive_entry_size(entry);
-                entries.push(ArchiveEntryInfo { path, size });
+                if options.selects(entry)? {
+                    entries.push(f(options, entry)?);
+                }
             }
         },
     )
@@ -277,7 +565,7 @@ pub fn list_archive_entries<R>(source: R) -> Result<Vec<ArchiveEntryInfo>>
 where
     R: Read + Seek,
 {
-    list_archive_entries_with_encoding(source, decode_utf8)
+    list_entries(source, &ReadOptions::default())
 }
 
 /// Uncompress a file using the `source` need as reader and the `target` as a
@@ -345,20 +633,135 @@ where
 /// # Ok(())
 /// # }
 /// ```
-pub fn uncompress_archive_with_encoding<R>(
+pub fn uncompress_archive_with_encoding<R, D>(
     source: R,
     dest: &Path,
     ownership: Ownership,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<()>
+where
+    R: Read + Seek,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+{
+    let options = ExtractOptions::new()
+        .ownership(ownership)
+        .read_options(ReadOptions::new().decoder(decode));
+    extract_to_dir(source, dest, &options)?;
+    Ok(())
+}
+
+/// Make libarchive use the ids stored in the archive as-is when `options`
+/// ask for [`OwnerLookup::Ids`].
+pub(crate) unsafe fn apply_owner_lookup(
+    options: &ExtractOptions,
+    archive_writer: *mut ffi::archive,
+) -> Result<()> {
+    if let OwnerLookup::Ids = options.owner_lookup {
+        // Without lookup functions, libarchive uses the ids as-is.
+        archive_result(
+            ffi::archive_write_disk_set_user_lookup(
+                archive_writer,
+                std::ptr::null_mut(),
+                None,
+                None,
+            ),
+            archive_writer,
+        )?;
+        archive_result(
+            ffi::archive_write_disk_set_group_lookup(
+                archive_writer,
+                std::ptr::null_mut(),
+                None,
+                None,
+            ),
+            archive_writer,
+        )?;
+    }
+    Ok(())
+}
+
+/// Apply the link, special file, permission and ownership settings of
+/// `options` to `entry`, to be extracted as `relative` after `symlinks`.
+/// Returns the setuid, setgid and sticky bits stripped from its mode, or
+/// `None` if the entry is to be skipped.
+pub(crate) unsafe fn apply_entry_options<F>(
+    options: &ExtractOptions,
+    archive_writer: *mut ffi::archive,
+    entry: *mut ffi::archive_entry,
+    relative: &Path,
+    symlinks: &ExtractedSymlinks,
+    entry_path: F,
+) -> Result<Option<u32>>
+where
+    F: Fn(&[u8]) -> Result<PathBuf>,
+{
+    let is_symlink = ffi::archive_entry_filetype(entry) as u32 == ffi::AE_IFLNK;
+    if options.links.skips_links() && (is_symlink || !ffi::archive_entry_hardlink(entry).is_null())
+    {
+        return Ok(None);
+    }
+    if let Some(target) = libarchive_entry_symlink(entry, entry_path)? {
+        options.links.check_symlink(relative, &target, symlinks)?;
+    }
+    if libarchive_entry_is_special(entry) {
+        match options.special_files {
+            SpecialFilePolicy::Allow => {}
+            SpecialFilePolicy::Skip => return Ok(None),
+            SpecialFilePolicy::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("special file {} is not allowed", relative.display()),
+                )
+                .into())
+            }
+        }
+    }
+    let perm = ffi::archive_entry_perm(entry) as u32;
+    let stripped_mode = if options.strip_special_bits {
+        perm & 0o7000
+    } else {
+        0
+    };
+    let mode = options.mode(
+        perm & !stripped_mode,
+        ffi::archive_entry_filetype(entry) as u32,
+    );
+    if mode != perm {
+        ffi::archive_entry_set_perm(entry, mode as _);
+    }
+    if let Ownership::Preserve = options.ownership {
+        options.owner_mapping.apply(archive_writer, entry)?;
+    }
+    Ok(Some(stripped_mode))
+}
+
+fn extract_to_dir<R>(mut source: R, dest: &Path, options: &ExtractOptions) -> Result<ExtractReport>
 where
     R: Read + Seek,
 {
     let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    let resolved = options.read.resolve(&mut source);
+    let read_options = &*resolved;
+    let entry_path = |bytes: &[u8]| -> Result<PathBuf> {
+        if options.verbatim_names {
+            Ok(PathBuf::from(os_string_from_bytes(bytes.to_vec())))
+        } else {
+            Ok(PathBuf::from(read_options.decode(bytes)?))
+        }
+    };
+    let mut report = ExtractReport::default();
+    // Entries extracted under another name, for hard links to follow them.
+    let mut renamed = HashMap::new();
+    // Paths written so far, which later entries replace regardless of the
+    // conflict policy.
+    let mut written = HashSet::new();
+    let mut symlinks = ExtractedSymlinks::default();
     run_with_archive(
-        ownership,
+        options.writer_flags(),
+        read_options,
         source,
         |archive_reader, archive_writer, mut entry| unsafe {
+            apply_owner_lookup(options, archive_writer)?;
             loop {
                 match ffi::archive_read_next_header(archive_reader, &mut entry) {
                     ffi::ARCHIVE_EOF => return Ok(()),
@@ -366,28 +769,50 @@ where
                 }
 
                 let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
+                if !read_options.selects(entry)? {
+                    continue;
+                }
                 let cstr = libarchive_entry_pathname(entry)?;
-                let target_path = CString::new(
-                    dest.join(sanitize_destination_path(Path::new(&decode(
-                        cstr.to_bytes(),
-                    )?))?)
-                    .to_str()
-                    .unwrap(),
-                )
-                .unwrap();
+                let relative = sanitize_destination_path(&entry_path(cstr.to_bytes())?)?.to_owned();
+                let Some(stripped_mode) = apply_entry_options(
+                    options,
+                    archive_writer,
+                    entry,
+                    &relative,
+                    &symlinks,
+                    entry_path,
+                )?
+                else {
+                    report.push(relative, EntryAction::Skipped, 0);
+                    continue;
+                };
+                let link_name = ffi::archive_entry_hardlink(entry);
+                let action = options.conflict.resolve(dest, &relative, entry, &written)?;
+                let extracted = match &action {
+                    EntryAction::Skipped => None,
+                    EntryAction::Renamed(to) => {
+                        renamed.insert(relative.clone(), to.clone());
+                        Some(to.clone())
+                    }
+                    _ => Some(relative.clone()),
+                };
+                report.push(relative, action, stripped_mode);
+                let Some(extracted) = extracted else {
+                    continue;
+                };
+                written.insert(extracted.clone());
+                let target_path = path_to_cstring(&dest.join(&extracted))?;
 
                 ffi::archive_entry_set_pathname(entry, target_path.as_ptr());
 
-                let link_name = ffi::archive_entry_hardlink(entry);
-                if !link_name.is_null() {
-                    let target_path = CString::new(
-                        dest.join(sanitize_destination_path(Path::new(&decode(
-                            CStr::from_ptr(link_name).to_bytes(),
-                        )?))?)
-                        .to_str()
-                        .unwrap(),
-                    )
-                    .unwrap();
+                if link_name.is_null() {
+                    symlinks.record(&extracted, libarchive_entry_symlink(entry, entry_path)?);
+                } else {
+                    let link = entry_path(CStr::from_ptr(link_name).to_bytes())?;
+                    let link = sanitize_destination_path(&link)?;
+                    let link = renamed.get(link).map_or(link, PathBuf::as_path);
+                    symlinks.record_hardlink(&extracted, link);
+                    let target_path = path_to_cstring(&dest.join(link))?;
 
                     ffi::archive_entry_set_hardlink(entry, target_path.as_ptr());
                 }
@@ -406,7 +831,8 @@ where
                 )?;
             }
         },
-    )
+    )?;
+    Ok(report)
 }
 
 /// Uncompress an archive using `source` as a reader and `dest` as the
@@ -431,7 +857,161 @@ pub fn uncompress_archive<R>(source: R, dest: &Path, ownership: Ownership) -> Re
 where
     R: Read + Seek,
 {
-    uncompress_archive_with_encoding(source, dest, ownership, decode_utf8)
+    extract_to_dir(source, dest, &ExtractOptions::new().ownership(ownership))?;
+    Ok(())
+}
+
+/// Uncompress an archive using `source` as a reader and `dest` as the
+/// destination directory, configured through [`ExtractOptions`].
+/// Returns what was done with each entry; see [`ConflictPolicy`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+/// use std::path::Path;
+///
+/// let mut source = File::open("tree.zip")?;
+/// let dest = Path::new("/tmp/dest");
+/// let options = ExtractOptions::new()
+///     .ownership(Ownership::Preserve)
+///     .read_options(ReadOptions::new().option(ReadOption::ZipIgnoreCrc32));
+///
+/// uncompress_archive_with_options(&mut source, &dest, options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_with_options<R>(
+    source: R,
+    dest: &Path,
+    options: ExtractOptions<'_>,
+) -> Result<ExtractReport>
+where
+    R: Read + Seek,
+{
+    extract_to_dir(source, dest, &options)
+}
+
+/// Uncompress an archive into `sink` instead of the local disk, e.g. object
+/// storage or an in-memory tree. See [`ExtractSink`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar.gz")?;
+/// let mut sink = DiskSink::new("/tmp/dest").ownership(Ownership::Preserve);
+///
+/// uncompress_archive_into(&mut source, &mut sink)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_into<R, S>(source: R, sink: &mut S) -> Result<()>
+where
+    R: Read + Seek,
+    S: ExtractSink,
+{
+    sink::extract_into(source, sink, &ExtractOptions::new())
+}
+
+/// Uncompress an archive into `sink`, configured through [`ExtractOptions`].
+/// See [`uncompress_archive_into`].
+///
+/// The link, special file, permission and owner mapping settings are applied
+/// to the entries and the [`EntryMetadata`] handed to the sink; owners are
+/// only mapped with [`Ownership::Preserve`]. Special files are never
+/// extracted, as there is no sink method for them. The [`ConflictPolicy`]
+/// does not apply: what happens to entries already in the destination is up
+/// to the sink. Neither does [`ExtractOptions::sparse`]: the holes of sparse
+/// entries are written to the sink as zeros.
+pub fn uncompress_archive_into_with_options<R, S>(
+    source: R,
+    sink: &mut S,
+    options: ExtractOptions<'_>,
+) -> Result<()>
+where
+    R: Read + Seek,
+    S: ExtractSink,
+{
+    sink::extract_into(source, sink, &options)
+}
+
+/// Uncompress an archive below the open directory `dir`, resolving every
+/// path relative to it without following symbolic links. Unlike
+/// [`uncompress_archive`], this is safe against concurrent modifications of
+/// the destination tree; see [`DirFdSink`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let source = File::open("tree.tar.gz")?;
+/// let dest = File::open("/tmp/dest")?;
+///
+/// uncompress_archive_at(source, &dest, Ownership::Ignore)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+pub fn uncompress_archive_at<R, D>(source: R, dir: D, ownership: Ownership) -> Result<()>
+where
+    R: Read + Seek,
+    D: std::os::fd::AsFd,
+{
+    let mut sink = DirFdSink::new(dir.as_fd()).ownership(ownership);
+    uncompress_archive_into(source, &mut sink)
+}
+
+/// Uncompress an archive into a [`MemoryTree`], e.g. to assert on its
+/// content in tests without extracting to disk.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let source = File::open("tree.tar.gz")?;
+/// let tree = uncompress_archive_to_memory(source)?;
+///
+/// for (path, node) in &tree {
+///     println!("{}: {:o}", path.display(), node.metadata().mode);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_to_memory<R>(source: R) -> Result<MemoryTree>
+where
+    R: Read + Seek,
+{
+    uncompress_archive_to_memory_with_options(source, ReadOptions::default())
+}
+
+/// Uncompress an archive into a [`MemoryTree`], using `options` to configure
+/// the libarchive reader.
+pub fn uncompress_archive_to_memory_with_options<R>(
+    source: R,
+    options: ReadOptions<'_>,
+) -> Result<MemoryTree>
+where
+    R: Read + Seek,
+{
+    let mut tree = MemoryTree::new();
+    sink::extract_into(
+        source,
+        &mut tree,
+        &ExtractOptions::new().read_options(options),
+    )?;
+    Ok(tree)
 }
 
 /// Uncompress a specific file from an archive. The `source` is used as a
@@ -453,19 +1033,36 @@ where
 /// # Ok(())
 /// # }
 /// ```
-pub fn uncompress_archive_file_with_encoding<R, W>(
+pub fn uncompress_archive_file_with_encoding<R, W, D>(
     source: R,
     target: W,
     path: &str,
-    decode: DecodeCallback,
+    decode: D,
 ) -> Result<usize>
 where
     R: Read + Seek,
     W: Write,
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+{
+    extract_file(
+        source,
+        path,
+        &ReadOptions::new().decoder(decode),
+        |reader| unsafe { libarchive_write_data_block(reader, target) },
+    )
+}
+
+fn extract_file<R, F>(mut source: R, path: &str, options: &ReadOptions, write: F) -> Result<usize>
+where
+    R: Read + Seek,
+    F: FnOnce(*mut ffi::archive) -> Result<usize>,
 {
     let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    let resolved = options.resolve(&mut source);
+    let options = &*resolved;
     run_with_archive(
-        Ownership::Ignore,
+        DISK_WRITER_FLAGS,
+        options,
         source,
         |archive_reader, _, mut entry| unsafe {
             loop {
@@ -482,7 +1079,7 @@ where
 
                 let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
                 let cstr = libarchive_entry_pathname(entry)?;
-                let file_name = decode(cstr.to_bytes())?;
+                let file_name = options.decode(cstr.to_bytes())?;
                 if file_name == path {
                     break;
                 }
@@ -491,7 +1088,7 @@ where
             if libarchive_entry_is_dir(entry) {
                 return Ok(0);
             }
-            libarchive_write_data_block(archive_reader, target)
+            write(archive_reader)
         },
     )
 }
@@ -519,10 +1116,240 @@ where
     R: Read + Seek,
     W: Write,
 {
-    uncompress_archive_file_with_encoding(source, target, path, decode_utf8)
+    extract_file(source, path, &ReadOptions::default(), |reader| unsafe {
+        libarchive_write_data_block(reader, target)
+    })
+}
+
+/// Uncompress a specific file from an archive, using `options` to configure
+/// the libarchive reader. The `source` is used as a reader, the `target` as
+/// a writer and the `path` is the relative path for the file to be extracted
+/// from the archive.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.zip")?;
+/// let mut target = Vec::default();
+/// let options = ReadOptions::new().option(ReadOption::ZipIgnoreCrc32);
+///
+/// uncompress_archive_file_with_options(&mut source, &mut target, "file/path", options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_file_with_options<R, W>(
+    source: R,
+    target: W,
+    path: &str,
+    options: ReadOptions<'_>,
+) -> Result<usize>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    extract_file(source, path, &options, |reader| unsafe {
+        libarchive_write_data_block(reader, target)
+    })
+}
+
+/// Uncompress a specific file from an archive, like
+/// [`uncompress_archive_file`], seeking over the holes of sparse entries
+/// instead of writing zeros.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("disk-image.tar")?;
+/// let mut target = File::create("/tmp/disk.img")?;
+///
+/// uncompress_archive_file_sparse(&mut source, &mut target, "disk.img")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_file_sparse<R, W>(source: R, target: W, path: &str) -> Result<usize>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    uncompress_archive_file_sparse_with_options(source, target, path, ReadOptions::default())
+}
+
+/// Uncompress a specific file from an archive, seeking over the holes of
+/// sparse entries, using `options` to configure the libarchive reader. See
+/// [`uncompress_archive_file_sparse`].
+pub fn uncompress_archive_file_sparse_with_options<R, W>(
+    source: R,
+    target: W,
+    path: &str,
+    options: ReadOptions<'_>,
+) -> Result<usize>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    extract_file(source, path, &options, |reader| unsafe {
+        libarchive_write_sparse_data_block(reader, target)
+    })
+}
+
+fn extract_files<R, P, W, I>(
+    mut source: R,
+    requests: I,
+    options: &ReadOptions,
+) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+    P: Into<String>,
+    W: Write,
+    I: IntoIterator<Item = (P, W)>,
+{
+    let mut order = Vec::new();
+    let mut pending: HashMap<String, Vec<W>> = HashMap::new();
+    for (path, target) in requests {
+        let path = path.into();
+        pending
+            .entry(path.clone())
+            .or_insert_with(|| {
+                order.push(path);
+                Vec::new()
+            })
+            .push(target);
+    }
+
+    let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    let resolved = options.resolve(&mut source);
+    let options = &*resolved;
+    run_with_archive(
+        DISK_WRITER_FLAGS,
+        options,
+        source,
+        |archive_reader, _, mut entry| unsafe {
+            while !pending.is_empty() {
+                match ffi::archive_read_next_header(archive_reader, &mut entry) {
+                    ffi::ARCHIVE_EOF => break,
+                    value => archive_result(value, archive_reader)?,
+                }
+
+                let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
+                let cstr = libarchive_entry_pathname(entry)?;
+                let file_name = options.decode(cstr.to_bytes())?;
+                let Some(mut targets) = pending.remove(&file_name) else {
+                    continue;
+                };
+                if libarchive_entry_is_dir(entry) {
+                    continue;
+                }
+                libarchive_write_data_block(archive_reader, MultiWriter(&mut targets))?;
+            }
+
+            Ok(order
+                .into_iter()
+                .filter(|path| pending.contains_key(path))
+                .collect())
+        },
+    )
+}
+
+/// Copies everything written into all the writers requesting the same path.
+struct MultiWriter<'a, W>(&'a mut [W]);
+
+impl<W> Write for MultiWriter<'_, W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for target in self.0.iter_mut() {
+            target.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(Write::flush)
+    }
 }
 
-fn run_with_archive<F, R, T>(ownership: Ownership, mut reader: R, f: F) -> Result<T>
+/// Uncompress several files from an archive in a single pass. Each request
+/// pairs the relative path of a file inside the archive with the writer its
+/// content goes to. Returns the requested paths not found in the archive, in
+/// the order they were requested.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar.gz")?;
+/// let mut first = Vec::default();
+/// let mut second = Vec::default();
+///
+/// let missing = uncompress_archive_files(
+///     &mut source,
+///     [("file/first", &mut first), ("file/second", &mut second)],
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_files<R, P, W, I>(source: R, requests: I) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+    P: Into<String>,
+    W: Write,
+    I: IntoIterator<Item = (P, W)>,
+{
+    extract_files(source, requests, &ReadOptions::default())
+}
+
+/// Uncompress several files from an archive in a single pass, using
+/// `options` to configure the libarchive reader. See
+/// [`uncompress_archive_files`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.zip")?;
+/// let mut first = Vec::default();
+/// let options = ReadOptions::new().password(ArchivePassword::new("secret")?);
+///
+/// let missing =
+///     uncompress_archive_files_with_options(&mut source, [("file/first", &mut first)], options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn uncompress_archive_files_with_options<R, P, W, I>(
+    source: R,
+    requests: I,
+    options: ReadOptions<'_>,
+) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+    P: Into<String>,
+    W: Write,
+    I: IntoIterator<Item = (P, W)>,
+{
+    extract_files(source, requests, &options)
+}
+
+fn run_with_archive<F, R, T>(
+    writer_flags: u32,
+    options: &ReadOptions,
+    mut reader: R,
+    f: F,
+) -> Result<T>
 where
     F: FnOnce(*mut ffi::archive, *mut ffi::archive, *mut ffi::archive_entry) -> Result<T>,
     R: Read + Seek,
@@ -547,16 +1374,6 @@ where
                 archive_reader,
             )?;
 
-            let mut writer_flags = ffi::ARCHIVE_EXTRACT_TIME
-                | ffi::ARCHIVE_EXTRACT_PERM
-                | ffi::ARCHIVE_EXTRACT_ACL
-                | ffi::ARCHIVE_EXTRACT_FFLAGS
-                | ffi::ARCHIVE_EXTRACT_XATTR;
-
-            if let Ownership::Preserve = ownership {
-                writer_flags |= ffi::ARCHIVE_EXTRACT_OWNER;
-            };
-
             archive_result(
                 ffi::archive_write_disk_set_options(archive_writer, writer_flags as i32),
                 archive_writer,
@@ -569,6 +1386,7 @@ where
                 ffi::archive_read_support_format_all(archive_reader),
                 archive_reader,
             )?;
+            options.apply(archive_reader)?;
 
             if archive_reader.is_null() || archive_writer.is_null() {
                 return Err(Error::NullArchive);
@@ -662,6 +1480,32 @@ where
     }
 }
 
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(unix)]
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))
+}
+
+#[cfg(not(unix))]
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"))?;
+    CString::new(path).map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))
+}
+
 // This ensures we're not affected by the zip-slip vulnerability. In summary, it
 // uses relative destination paths to unpack files in unexpected places. This
 // also handles absolute paths, where the leading '/' will be stripped, matching
@@ -715,19 +1559,43 @@ fn libarchive_copy_data(
 fn libarchive_entry_size(entry: *mut ffi::archive_entry) -> u64 {
     // `st_size` is `i32` on Windows (see the `stat` struct above) and `i64`
     // on Unix. Widen through `i64` to keep the cast platform-agnostic.
+    #[allow(clippy::unnecessary_cast)]
     let size = unsafe { (*ffi::archive_entry_stat(entry)).st_size } as i64;
     size.max(0) as u64
 }
 
+fn libarchive_entry_mtime(entry: *mut ffi::archive_entry) -> SystemTime {
+    let (secs, nanos) = unsafe {
+        (
+            ffi::archive_entry_mtime(entry) as i64,
+            ffi::archive_entry_mtime_nsec(entry).max(0) as u64,
+        )
+    };
+    let nanos = Duration::from_nanos(nanos);
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64) + nanos
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + nanos
+    }
+}
+
 // Raw POSIX mode bits: `libc::S_IFDIR` is not exposed on Windows, where our
 // `stat` mirrors libarchive's own layout.
 pub(crate) fn libarchive_entry_is_dir(entry: *mut ffi::archive_entry) -> bool {
     const S_IFMT: u32 = 0o170000;
     const S_IFDIR: u32 = 0o040000;
+    #[allow(clippy::unnecessary_cast)]
     let mode = unsafe { (*ffi::archive_entry_stat(entry)).st_mode } as u32;
     (mode & S_IFMT) == S_IFDIR
 }
 
+fn libarchive_entry_is_special(entry: *mut ffi::archive_entry) -> bool {
+    matches!(
+        unsafe { ffi::archive_entry_filetype(entry) } as u32,
+        ffi::AE_IFCHR | ffi::AE_IFBLK | ffi::AE_IFIFO | ffi::AE_IFSOCK
+    )
+}
+
 fn libarchive_entry_pathname<'a>(entry: *mut ffi::archive_entry) -> Result<&'a CStr> {
     let pathname = unsafe { ffi::archive_entry_pathname(entry) };
     if pathname.is_null() {
@@ -741,31 +1609,96 @@ fn libarchive_entry_pathname<'a>(entry: *mut ffi::archive_entry) -> Result<&'a C
     Ok(unsafe { CStr::from_ptr(pathname) })
 }
 
+/// The target of `entry` if it is a symlink, decoded with `entry_path`.
+fn libarchive_entry_symlink<F>(
+    entry: *mut ffi::archive_entry,
+    entry_path: F,
+) -> Result<Option<PathBuf>>
+where
+    F: Fn(&[u8]) -> Result<PathBuf>,
+{
+    let target = unsafe { ffi::archive_entry_symlink(entry) };
+    if unsafe { ffi::archive_entry_filetype(entry) } as u32 != ffi::AE_IFLNK || target.is_null() {
+        return Ok(None);
+    }
+
+    entry_path(unsafe { CStr::from_ptr(target) }.to_bytes()).map(Some)
+}
+
 unsafe fn libarchive_write_data_block<W>(
+    archive_reader: *mut ffi::archive,
+    target: W,
+) -> Result<usize>
+where
+    W: Write,
+{
+    // Holes of sparse entries are written out as zeros.
+    libarchive_write_data_blocks(archive_reader, target, |target, hole, _| {
+        io::copy(&mut io::repeat(0).take(hole), target).map(drop)
+    })
+}
+
+unsafe fn libarchive_write_sparse_data_block<W>(
+    archive_reader: *mut ffi::archive,
+    target: W,
+) -> Result<usize>
+where
+    W: Write + Seek,
+{
+    libarchive_write_data_blocks(archive_reader, target, |target, hole, trailing| {
+        if trailing {
+            // Seeking alone does not extend the target.
+            target.seek(SeekFrom::Current(hole as i64 - 1))?;
+            target.write_all(&[0])
+        } else {
+            target.seek(SeekFrom::Current(hole as i64)).map(drop)
+        }
+    })
+}
+
+/// Copy the data of the current entry to `target`, calling `skip` with the
+/// length of each hole of a sparse entry, and whether it ends the entry.
+/// Returns the size of the entry.
+unsafe fn libarchive_write_data_blocks<W, S>(
     archive_reader: *mut ffi::archive,
     mut target: W,
+    mut skip: S,
 ) -> Result<usize>
 where
     W: Write,
+    S: FnMut(&mut W, u64, bool) -> io::Result<()>,
 {
     let mut buffer = std::ptr::null();
     let mut offset = 0;
     let mut size = 0;
-    let mut written = 0;
+    let mut position = 0;
 
     loop {
         match ffi::archive_read_data_block(archive_reader, &mut buffer, &mut size, &mut offset) {
-            ffi::ARCHIVE_EOF => return Ok(written),
+            ffi::ARCHIVE_EOF => {
+                // The offset is now the end of the entry, past any final hole.
+                let end = offset.max(0) as u64;
+                if end > position {
+                    skip(&mut target, end - position, true)?;
+                    position = end;
+                }
+                return Ok(position as usize);
+            }
             value => archive_result(value, archive_reader)?,
         }
 
+        let offset = offset.max(0) as u64;
+        if offset > position {
+            skip(&mut target, offset - position, false)?;
+            position = offset;
+        }
         if size == 0 {
             continue;
         }
 
         let content = slice::from_raw_parts(buffer as *const u8, size);
         target.write_all(content)?;
-        written += size;
+        position += size as u64;
     }
 }
 
@@ -0,0 +1,224 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Restrictions on the symbolic and hard links an archive may create.
+
+use crate::Result;
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+// Bound on the symlinks followed while resolving a single path, as Linux's
+// MAXSYMLINKS.
+const MAX_SYMLINK_EXPANSIONS: usize = 40;
+
+/// Which links [`uncompress_archive_with_options`] creates.
+///
+/// Entry paths and hard link targets are always kept inside the destination,
+/// but symlink targets are otherwise written verbatim: an archive holding
+/// `evil -> /etc` followed by `evil/passwd` writes outside of it. The
+/// default policy allows everything, as libarchive does;
+/// [`LinkPolicy::strict`] is recommended for untrusted archives.
+///
+/// Rejected links fail the extraction with
+/// [`std::io::ErrorKind::InvalidData`].
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+/// use std::path::Path;
+///
+/// let mut source = File::open("upload.tar")?;
+/// let options = ExtractOptions::new().link_policy(LinkPolicy::strict());
+///
+/// uncompress_archive_with_options(&mut source, Path::new("/tmp/dest"), options)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`uncompress_archive_with_options`]: crate::uncompress_archive_with_options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[must_use]
+pub struct LinkPolicy {
+    reject_absolute: bool,
+    reject_escaping: bool,
+    secure_symlinks: bool,
+    skip_links: bool,
+}
+
+impl LinkPolicy {
+    /// Create a policy allowing every link.
+    pub fn new() -> LinkPolicy {
+        LinkPolicy::default()
+    }
+
+    /// Create a policy rejecting absolute and escaping symlinks, and
+    /// refusing to write through symlinks.
+    pub fn strict() -> LinkPolicy {
+        LinkPolicy::new()
+            .reject_absolute(true)
+            .reject_escaping(true)
+            .secure_symlinks(true)
+    }
+
+    /// Reject symlinks with an absolute target.
+    pub fn reject_absolute(mut self, enable: bool) -> LinkPolicy {
+        self.reject_absolute = enable;
+        self
+    }
+
+    /// Reject symlinks whose target, resolved from the link's directory,
+    /// leaves the destination, such as `../../etc`. Symlinks extracted
+    /// earlier from the same archive are followed while resolving, so
+    /// `sub/up -> ..` followed by `escape -> sub/up/..` is rejected too.
+    /// Absolute targets are rejected as well.
+    pub fn reject_escaping(mut self, enable: bool) -> LinkPolicy {
+        self.reject_escaping = enable;
+        self
+    }
+
+    /// Refuse to extract an entry whose path goes through a symlink, e.g.
+    /// one created by an earlier entry (libarchive's
+    /// `ARCHIVE_EXTRACT_SECURE_SYMLINKS`).
+    pub fn secure_symlinks(mut self, enable: bool) -> LinkPolicy {
+        self.secure_symlinks = enable;
+        self
+    }
+
+    /// Skip symlinks and hard links altogether. Skipped links are reported
+    /// as [`EntryAction::Skipped`](crate::EntryAction::Skipped).
+    pub fn skip_links(mut self, enable: bool) -> LinkPolicy {
+        self.skip_links = enable;
+        self
+    }
+
+    pub(crate) fn secure_symlinks_enabled(&self) -> bool {
+        self.secure_symlinks
+    }
+
+    pub(crate) fn skips_links(&self) -> bool {
+        self.skip_links
+    }
+
+    /// Check the symlink at `path`, relative to the destination, pointing
+    /// at `target`, given the `symlinks` extracted before it.
+    pub(crate) fn check_symlink(
+        &self,
+        path: &Path,
+        target: &Path,
+        symlinks: &ExtractedSymlinks,
+    ) -> Result<()> {
+        let rejected = |reason: &str| -> Result<()> {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "symlink {} -> {} {}",
+                    path.display(),
+                    target.display(),
+                    reason
+                ),
+            )
+            .into())
+        };
+
+        if target.has_root() {
+            if self.reject_absolute || self.reject_escaping {
+                return rejected("has an absolute target");
+            }
+            return Ok(());
+        }
+        if !self.reject_escaping {
+            return Ok(());
+        }
+
+        let parent = path.parent().unwrap_or(Path::new(""));
+        if let Err(reason) = symlinks.resolve(parent.components().chain(target.components())) {
+            return rejected(reason);
+        }
+        Ok(())
+    }
+}
+
+/// The symlinks extracted so far, by path relative to the destination, to
+/// resolve the targets of later symlinks through them.
+#[derive(Debug, Default)]
+pub(crate) struct ExtractedSymlinks(HashMap<PathBuf, PathBuf>);
+
+impl ExtractedSymlinks {
+    /// Record the entry extracted at `path`, with its `target` if it is a
+    /// symlink. Any symlink it replaces is forgotten.
+    pub(crate) fn record(&mut self, path: &Path, target: Option<PathBuf>) {
+        let location = self.location(path);
+        match target {
+            Some(target) => self.0.insert(location, target),
+            None => self.0.remove(&location),
+        };
+    }
+
+    /// Record the hard link extracted at `path` to `link`, which is a
+    /// symlink itself if `link` is one.
+    pub(crate) fn record_hardlink(&mut self, path: &Path, link: &Path) {
+        let target = self.0.get(&self.location(link)).cloned();
+        self.record(path, target);
+    }
+
+    /// Where `path` is created, once the symlinks among its parents are
+    /// followed.
+    fn location(&self, path: &Path) -> PathBuf {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => match self.resolve(parent.components()) {
+                Ok(parent) => parent.join(name),
+                Err(_) => path.to_path_buf(),
+            },
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Resolve `components` from the destination, following the recorded
+    /// symlinks. Fails with the reason if the path leaves the destination.
+    fn resolve<'a>(
+        &'a self,
+        components: impl IntoIterator<Item = Component<'a>>,
+    ) -> std::result::Result<PathBuf, &'static str> {
+        let mut pending: VecDeque<_> = components.into_iter().collect();
+        let mut resolved = PathBuf::new();
+        let mut expansions = 0;
+        while let Some(component) = pending.pop_front() {
+            match component {
+                Component::Normal(name) => {
+                    resolved.push(name);
+                    let Some(target) = self.0.get(&resolved) else {
+                        continue;
+                    };
+                    expansions += 1;
+                    if expansions > MAX_SYMLINK_EXPANSIONS {
+                        return Err("has too many levels of symbolic links");
+                    }
+                    if target.has_root() {
+                        return Err("goes through a symlink with an absolute target");
+                    }
+                    resolved.pop();
+                    for component in target.components().rev() {
+                        pending.push_front(component);
+                    }
+                }
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return Err("escapes the destination");
+                    }
+                }
+                Component::CurDir => {}
+                Component::RootDir | Component::Prefix(_) => {
+                    return Err("has an absolute target");
+                }
+            }
+        }
+        Ok(resolved)
+    }
+}
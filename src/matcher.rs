@@ -0,0 +1,343 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Entry selection by glob patterns, following GNU tar's `--wildcards`
+//! rules: `*` and `?` also match `/`, and a pattern matching a directory
+//! selects everything below it. Entries can further be narrowed down by
+//! modification time, size and ownership.
+
+use crate::{ffi, libarchive_entry_mtime};
+use std::{ffi::CStr, os::raw::c_char, time::SystemTime};
+
+/// Selects archive entries by include and exclude glob patterns.
+///
+/// An entry is selected when it matches any include pattern (or there are
+/// none) and no exclude pattern. Patterns support `*`, `?`, bracket
+/// expressions such as `[a-z]` or `[!0-9]`, and `\` to escape the next
+/// character.
+///
+/// As in tar, include patterns are anchored at the start of the path by
+/// default while exclude patterns are not, i.e. `exclude("*.o")` and
+/// `exclude("build")` also apply inside subdirectories.
+///
+/// Every other criterion set must hold too. Owners given several times
+/// through the same method are alternatives, e.g. `.uid(0).uid(1000)`
+/// selects entries owned by either.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar.gz")?;
+/// let matcher = EntryMatcher::new().include("src/*.rs").exclude("generated");
+/// let options = ReadOptions::new().matcher(matcher);
+///
+/// let sources = list_archive_files_with_options(&mut source, options)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Restoring the files a user changed during the last day:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+/// use std::path::Path;
+/// use std::time::{Duration, SystemTime};
+///
+/// let mut source = File::open("backup.tar")?;
+/// let matcher = EntryMatcher::new()
+///     .modified_after(SystemTime::now() - Duration::from_secs(24 * 60 * 60))
+///     .uid(1000)
+///     .max_size(100 * 1024 * 1024);
+/// let options = ExtractOptions::new().read_options(ReadOptions::new().matcher(matcher));
+///
+/// uncompress_archive_with_options(&mut source, Path::new("/tmp/restore"), options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use]
+pub struct EntryMatcher {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    uids: Vec<u64>,
+    gids: Vec<u64>,
+    unames: Vec<String>,
+    gnames: Vec<String>,
+}
+
+impl EntryMatcher {
+    /// Create a matcher selecting every entry.
+    pub fn new() -> EntryMatcher {
+        EntryMatcher::default()
+    }
+
+    /// Select entries whose path, from its start, matches `pattern`.
+    pub fn include<S: AsRef<str>>(mut self, pattern: S) -> EntryMatcher {
+        self.include.push(Pattern::new(pattern.as_ref(), true));
+        self
+    }
+
+    /// Select entries with any run of path components matching `pattern`,
+    /// like tar's `--no-anchored`.
+    pub fn include_unanchored<S: AsRef<str>>(mut self, pattern: S) -> EntryMatcher {
+        self.include.push(Pattern::new(pattern.as_ref(), false));
+        self
+    }
+
+    /// Skip entries with any run of path components matching `pattern`.
+    pub fn exclude<S: AsRef<str>>(mut self, pattern: S) -> EntryMatcher {
+        self.exclude.push(Pattern::new(pattern.as_ref(), false));
+        self
+    }
+
+    /// Skip entries whose path, from its start, matches `pattern`, like
+    /// tar's `--anchored --exclude`.
+    pub fn exclude_anchored<S: AsRef<str>>(mut self, pattern: S) -> EntryMatcher {
+        self.exclude.push(Pattern::new(pattern.as_ref(), true));
+        self
+    }
+
+    /// Select entries modified strictly after `time`.
+    pub fn modified_after(mut self, time: SystemTime) -> EntryMatcher {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Select entries modified strictly before `time`.
+    pub fn modified_before(mut self, time: SystemTime) -> EntryMatcher {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Select entries of at least `bytes` bytes.
+    pub fn min_size(mut self, bytes: u64) -> EntryMatcher {
+        self.min_size = Some(bytes);
+        self
+    }
+
+    /// Select entries of at most `bytes` bytes.
+    pub fn max_size(mut self, bytes: u64) -> EntryMatcher {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Select entries owned by user ID `uid`.
+    pub fn uid(mut self, uid: u64) -> EntryMatcher {
+        self.uids.push(uid);
+        self
+    }
+
+    /// Select entries owned by group ID `gid`.
+    pub fn gid(mut self, gid: u64) -> EntryMatcher {
+        self.gids.push(gid);
+        self
+    }
+
+    /// Select entries owned by the user named `uname` in the archive.
+    pub fn uname<S: Into<String>>(mut self, uname: S) -> EntryMatcher {
+        self.unames.push(uname.into());
+        self
+    }
+
+    /// Select entries owned by the group named `gname` in the archive.
+    pub fn gname<S: Into<String>>(mut self, gname: S) -> EntryMatcher {
+        self.gnames.push(gname.into());
+        self
+    }
+
+    /// Whether an entry at `path` is selected by the name patterns. The
+    /// other criteria are only checked against archive entries.
+    pub fn matches(&self, path: &str) -> bool {
+        let path: Vec<char> = path.trim_end_matches('/').chars().collect();
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(&path)))
+            && !self.exclude.iter().any(|p| p.matches(&path))
+    }
+
+    /// Whether `entry`, found at the decoded `path`, is selected.
+    pub(crate) unsafe fn matches_entry(&self, path: &str, entry: *mut ffi::archive_entry) -> bool {
+        if !self.matches(path) {
+            return false;
+        }
+
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let mtime = libarchive_entry_mtime(entry);
+            if self.modified_after.is_some_and(|after| mtime <= after)
+                || self.modified_before.is_some_and(|before| mtime >= before)
+            {
+                return false;
+            }
+        }
+
+        let size = ffi::archive_entry_size(entry).max(0) as u64;
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return false;
+        }
+
+        let uid = ffi::archive_entry_uid(entry).max(0) as u64;
+        let gid = ffi::archive_entry_gid(entry).max(0) as u64;
+        (self.uids.is_empty() || self.uids.contains(&uid))
+            && (self.gids.is_empty() || self.gids.contains(&gid))
+            && name_matches(&self.unames, ffi::archive_entry_uname(entry))
+            && name_matches(&self.gnames, ffi::archive_entry_gname(entry))
+    }
+}
+
+unsafe fn name_matches(names: &[String], name: *const c_char) -> bool {
+    if names.is_empty() {
+        return true;
+    }
+    if name.is_null() {
+        return false;
+    }
+    let name = CStr::from_ptr(name).to_bytes();
+    names.iter().any(|candidate| candidate.as_bytes() == name)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    Any,
+    Star,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Literal(literal) => *literal == c,
+            Token::Any => true,
+            Token::Star => false,
+            Token::Class { negated, ranges } => {
+                ranges.iter().any(|&(low, high)| low <= c && c <= high) != *negated
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Pattern {
+    tokens: Vec<Token>,
+    anchored: bool,
+}
+
+impl Pattern {
+    fn new(pattern: &str, anchored: bool) -> Pattern {
+        let chars: Vec<char> = pattern.trim_end_matches('/').chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' => {
+                    if tokens.last() != Some(&Token::Star) {
+                        tokens.push(Token::Star);
+                    }
+                }
+                '?' => tokens.push(Token::Any),
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+                    tokens.push(Token::Literal(chars[i]));
+                }
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += len;
+                    }
+                    // An unterminated bracket matches itself, as in fnmatch.
+                    None => tokens.push(Token::Literal('[')),
+                },
+                c => tokens.push(Token::Literal(c)),
+            }
+            i += 1;
+        }
+        Pattern { tokens, anchored }
+    }
+
+    /// Match the whole path or any leading run of its components; when not
+    /// anchored, also starting at any component.
+    fn matches(&self, path: &[char]) -> bool {
+        let mut starts = std::iter::once(0).chain(
+            path.iter()
+                .enumerate()
+                .filter(|&(_, &c)| c == '/')
+                .map(|(i, _)| i + 1)
+                .take_while(|_| !self.anchored),
+        );
+        starts.any(|start| {
+            let rest = &path[start..];
+            glob_match(&self.tokens, rest)
+                || rest
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == '/' && glob_match(&self.tokens, &rest[..i]))
+        })
+    }
+}
+
+/// Parse a bracket expression following its `[`. Returns the token and the
+/// number of characters consumed, including the closing `]`.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let c = *chars.get(i)?;
+        if c == ']' && !first {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        first = false;
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+}
+
+fn glob_match(tokens: &[Token], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it currently extends to.
+    let mut backtrack = None;
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(Token::Star) => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(token) if token.matches(text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[p..].iter().all(|token| *token == Token::Star)
+}
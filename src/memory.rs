@@ -0,0 +1,169 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! In-memory extraction target, to inspect archive contents without touching
+//! the disk.
+
+use crate::{EntryMetadata, ExtractSink, Result};
+use std::{
+    collections::{btree_map, BTreeMap},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Upper bound on the buffer reserved up front for a file, as the size in the
+/// entry header is not trusted.
+const MAX_PREALLOCATION: u64 = 1 << 20;
+
+/// An entry of a [`MemoryTree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoryNode {
+    Directory {
+        metadata: EntryMetadata,
+    },
+    /// A regular file. Hard links are stored as a copy of their target.
+    File {
+        data: Vec<u8>,
+        metadata: EntryMetadata,
+    },
+    Symlink {
+        target: PathBuf,
+        metadata: EntryMetadata,
+    },
+}
+
+impl MemoryNode {
+    /// The metadata stored in the archive for this entry.
+    pub fn metadata(&self) -> &EntryMetadata {
+        match self {
+            MemoryNode::Directory { metadata }
+            | MemoryNode::File { metadata, .. }
+            | MemoryNode::Symlink { metadata, .. } => metadata,
+        }
+    }
+}
+
+/// The entries of an archive, keyed by their sanitized relative path.
+///
+/// Build one with [`uncompress_archive_to_memory`](crate::uncompress_archive_to_memory),
+/// or use it as the [`ExtractSink`] of
+/// [`uncompress_archive_into`](crate::uncompress_archive_into) to collect
+/// several archives into the same tree.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let source = File::open("tree.tar")?;
+/// let tree = uncompress_archive_to_memory(source)?;
+///
+/// assert_eq!(tree.file("tree/branch1/leaf"), Some(&b"Hello World\n"[..]));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryTree {
+    nodes: BTreeMap<PathBuf, MemoryNode>,
+}
+
+impl MemoryTree {
+    /// Create an empty tree.
+    pub fn new() -> MemoryTree {
+        MemoryTree::default()
+    }
+
+    /// The entry at `path`, if any.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&MemoryNode> {
+        self.nodes.get(path.as_ref())
+    }
+
+    /// The content of the regular file at `path`, if any.
+    pub fn file<P: AsRef<Path>>(&self, path: P) -> Option<&[u8]> {
+        match self.get(path)? {
+            MemoryNode::File { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Iterate over the entries, sorted by path.
+    pub fn iter(&self) -> btree_map::Iter<'_, PathBuf, MemoryNode> {
+        self.nodes.iter()
+    }
+
+    /// Number of entries in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a MemoryTree {
+    type Item = (&'a PathBuf, &'a MemoryNode);
+    type IntoIter = btree_map::Iter<'a, PathBuf, MemoryNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl ExtractSink for MemoryTree {
+    type File<'a> = &'a mut Vec<u8>;
+
+    fn create_dir(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.nodes.insert(
+            path.to_path_buf(),
+            MemoryNode::Directory {
+                metadata: metadata.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<&mut Vec<u8>> {
+        let node = MemoryNode::File {
+            data: Vec::with_capacity(metadata.size.min(MAX_PREALLOCATION) as usize),
+            metadata: metadata.clone(),
+        };
+        self.nodes.insert(path.to_path_buf(), node);
+        match self.nodes.get_mut(path) {
+            Some(MemoryNode::File { data, .. }) => Ok(data),
+            _ => unreachable!(),
+        }
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.nodes.insert(
+            path.to_path_buf(),
+            MemoryNode::Symlink {
+                target: target.to_path_buf(),
+                metadata: metadata.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let node = self.nodes.get(target).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("hard link target {} was not extracted", target.display()),
+            )
+        })?;
+        self.nodes.insert(path.to_path_buf(), node);
+        Ok(())
+    }
+
+    fn set_metadata(&mut self, _: &Path, _: &EntryMetadata) -> Result<()> {
+        // Recorded when the entry was created.
+        Ok(())
+    }
+}
@@ -0,0 +1,528 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Reader and extraction settings accepted by the `_with_options` entry
+//! points and [`ArchiveIteratorBuilder`](crate::ArchiveIteratorBuilder).
+
+#[cfg(feature = "charset_detection")]
+use crate::ZipCharsetDetector;
+use crate::{
+    error::archive_result, ffi, libarchive_entry_pathname, ArchivePassword, ConflictPolicy,
+    DecodeCallback, EntryMatcher, Error, LinkPolicy, OwnerLookup, OwnerMapping, Ownership, Result,
+    DISK_WRITER_FLAGS,
+};
+use std::{
+    borrow::Cow,
+    ffi::CString,
+    fmt,
+    io::{Read, Seek},
+    os::raw::{c_char, c_void},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// A single libarchive reader option, forwarded to
+/// `archive_read_set_options(3)`.
+///
+/// The typed variants cover the options most commonly needed to read
+/// archives produced by other tools; anything else can be passed through
+/// [`ReadOption::Raw`] using libarchive's `module:option=value` syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReadOption {
+    /// `zip:ignorecrc32` — do not verify the CRC-32 of ZIP entry data.
+    ZipIgnoreCrc32,
+    /// `hdrcharset=<charset>` — charset used to interpret pathnames stored
+    /// in archive headers, e.g. `CP932` for ZIPs created on Japanese
+    /// Windows.
+    HdrCharset(String),
+    /// `iso9660:rockridge` or `iso9660:!rockridge` — toggle Rock Ridge
+    /// extensions when reading ISO images.
+    Iso9660RockRidge(bool),
+    /// `tar:read_concatenated_archives` — keep reading past the end-of-archive
+    /// marker, as produced by `cat a.tar b.tar`.
+    TarReadConcatenatedArchives,
+    /// `mac-ext` — decode macOS metadata stored in ZIP extra fields.
+    MacExt,
+    /// An option string passed verbatim to libarchive.
+    Raw(String),
+}
+
+impl fmt::Display for ReadOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadOption::ZipIgnoreCrc32 => f.write_str("zip:ignorecrc32"),
+            ReadOption::HdrCharset(charset) => write!(f, "hdrcharset={}", charset),
+            ReadOption::Iso9660RockRidge(true) => f.write_str("iso9660:rockridge"),
+            ReadOption::Iso9660RockRidge(false) => f.write_str("iso9660:!rockridge"),
+            ReadOption::TarReadConcatenatedArchives => {
+                f.write_str("tar:read_concatenated_archives")
+            }
+            ReadOption::MacExt => f.write_str("mac-ext"),
+            ReadOption::Raw(option) => f.write_str(option),
+        }
+    }
+}
+
+/// Settings applied to the libarchive reader before the archive is opened.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("legacy.zip")?;
+/// let options = ReadOptions::new()
+///     .option(ReadOption::HdrCharset("CP932".to_string()))
+///     .option(ReadOption::ZipIgnoreCrc32);
+///
+/// let file_list = list_archive_files_with_options(&mut source, options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+#[must_use]
+pub struct ReadOptions<'a> {
+    format_options: Vec<ReadOption>,
+    passwords: Vec<ArchivePassword>,
+    passphrase_callback: Option<Arc<PassphraseCallback<'a>>>,
+    decoder: Option<Arc<DecodeCallback>>,
+    #[cfg(feature = "charset_detection")]
+    charset_detector: Option<ZipCharsetDetector>,
+    matcher: Option<EntryMatcher>,
+}
+
+impl<'a> ReadOptions<'a> {
+    /// Create an empty set of reader options, matching the behavior of the
+    /// entry points that take no options.
+    pub fn new() -> ReadOptions<'a> {
+        ReadOptions::default()
+    }
+
+    /// Add a libarchive reader option. Options are applied in the order
+    /// they were added; an option libarchive does not recognize makes the
+    /// archive fail to open with [`Error::Extraction`].
+    pub fn option(mut self, option: ReadOption) -> ReadOptions<'a> {
+        self.format_options.push(option);
+        self
+    }
+
+    /// Add a password to decrypt encrypted archive entries, e.g. of a ZIP
+    /// archive. May be called multiple times; libarchive tries each password
+    /// in turn until one decrypts the entry.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use compress_tools::*;
+    /// use std::fs::File;
+    /// use std::path::Path;
+    ///
+    /// let mut source = File::open("encrypted.zip")?;
+    /// let read = ReadOptions::new()
+    ///     .password(ArchivePassword::new("old secret")?)
+    ///     .password(ArchivePassword::new("new secret")?);
+    ///
+    /// uncompress_archive_with_options(
+    ///     &mut source,
+    ///     Path::new("/tmp/dest"),
+    ///     ExtractOptions::new().read_options(read),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn password(mut self, password: ArchivePassword) -> ReadOptions<'a> {
+        self.passwords.push(password);
+        self
+    }
+
+    /// Ask `callback` for a password whenever libarchive needs one and the
+    /// passwords added with [`password`](ReadOptions::password) did not work,
+    /// e.g. to prompt the user or query a secrets store.
+    ///
+    /// The callback is invoked again after each wrong guess until it returns
+    /// `None`, at which point reading the entry fails. Passwords it returns
+    /// are remembered for the following entries.
+    ///
+    /// The callback only has to outlive the options, so the one-shot
+    /// functions accept closures borrowing local state:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use compress_tools::*;
+    /// use std::fs::File;
+    ///
+    /// let candidates = vec!["summer", "winter"];
+    /// let mut guesses = candidates.iter();
+    ///
+    /// let mut source = File::open("encrypted.zip")?;
+    /// let mut target = Vec::default();
+    /// let options = ReadOptions::new()
+    ///     .passphrase_callback(|| guesses.next().and_then(|p| ArchivePassword::new(p).ok()));
+    ///
+    /// uncompress_archive_file_with_options(&mut source, &mut target, "secret.txt", options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn passphrase_callback<F>(mut self, callback: F) -> ReadOptions<'a>
+    where
+        F: FnMut() -> Option<ArchivePassword> + Send + 'a,
+    {
+        self.passphrase_callback = Some(Arc::new(PassphraseCallback {
+            state: Mutex::new(PassphraseState {
+                callback: Box::new(callback),
+                last: None,
+            }),
+        }));
+        self
+    }
+
+    /// Use a custom decoder to decode filenames of archive entries.
+    /// By default an UTF-8 decoder is used.
+    ///
+    /// The decoder may capture state, so the encoding can be chosen at
+    /// runtime:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use compress_tools::*;
+    /// use std::fs::File;
+    ///
+    /// let codepage = std::env::var("ARCHIVE_CODEPAGE")?;
+    /// let decoder = move |bytes: &[u8]| match codepage.as_str() {
+    ///     "utf-8" => Ok(std::str::from_utf8(bytes)?.to_owned()),
+    ///     _ => Ok(String::from_utf8_lossy(bytes).into_owned()),
+    /// };
+    ///
+    /// let mut source = File::open("tree.zip")?;
+    /// let options = ReadOptions::new().decoder(decoder);
+    /// let file_list = list_archive_files_with_options(&mut source, options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decoder<D>(mut self, decoder: D) -> ReadOptions<'a>
+    where
+        D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+    {
+        self.decoder = Some(Arc::new(decoder));
+        self
+    }
+
+    /// Detect the charset of ZIP entry names that are not flagged as UTF-8,
+    /// instead of requiring the codepage to be known in advance. See
+    /// [`ZipCharsetDetector`] for how the charset is chosen. Names the
+    /// detector cannot decode, and non-ZIP archives, go through the
+    /// [`decoder`](ReadOptions::decoder).
+    #[cfg(feature = "charset_detection")]
+    pub fn detect_zip_charset(mut self, detector: ZipCharsetDetector) -> ReadOptions<'a> {
+        self.charset_detector = Some(detector);
+        self
+    }
+
+    /// Only list, extract or iterate over entries selected by `matcher`.
+    /// Functions looking up a single path ignore it.
+    pub fn matcher(mut self, matcher: EntryMatcher) -> ReadOptions<'a> {
+        self.matcher = Some(matcher);
+        self
+    }
+
+    /// Whether `entry` is selected by the [`matcher`](ReadOptions::matcher).
+    pub(crate) unsafe fn selects(&self, entry: *mut ffi::archive_entry) -> Result<bool> {
+        match &self.matcher {
+            Some(matcher) => {
+                let path = self.decode(libarchive_entry_pathname(entry)?.to_bytes())?;
+                Ok(matcher.matches_entry(&path, entry))
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Resolve settings that depend on the archive contents, currently the
+    /// ZIP filename charset. Leaves `reader` at the start of the archive.
+    pub(crate) fn resolve<R: Read + Seek>(&self, reader: &mut R) -> Cow<'_, ReadOptions<'a>> {
+        #[cfg(feature = "charset_detection")]
+        if let Some(detector) = &self.charset_detector {
+            let entries = crate::zip_preflight::central_directory(reader);
+            if let Some(decoder) = detector.decoder(entries, self.decoder_fn()) {
+                let mut resolved = self.clone();
+                resolved.decoder = Some(decoder);
+                resolved.charset_detector = None;
+                return Cow::Owned(resolved);
+            }
+        }
+        #[cfg(not(feature = "charset_detection"))]
+        let _ = reader;
+        Cow::Borrowed(self)
+    }
+
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<String> {
+        match &self.decoder {
+            Some(decoder) => decoder(bytes),
+            None => crate::decode_utf8(bytes),
+        }
+    }
+
+    #[cfg(feature = "charset_detection")]
+    pub(crate) fn decoder_fn(&self) -> Arc<DecodeCallback> {
+        self.decoder
+            .clone()
+            .unwrap_or_else(|| Arc::new(crate::decode_utf8))
+    }
+
+    /// Configure `archive_reader` with these options. The options must stay
+    /// alive until the reader is freed, as libarchive keeps a pointer to the
+    /// passphrase callback.
+    pub(crate) unsafe fn apply(&self, archive_reader: *mut ffi::archive) -> Result<()> {
+        for password in &self.passwords {
+            archive_result(
+                ffi::archive_read_add_passphrase(archive_reader, password.as_ptr()),
+                archive_reader,
+            )?;
+        }
+        if let Some(callback) = &self.passphrase_callback {
+            archive_result(
+                ffi::archive_read_set_passphrase_callback(
+                    archive_reader,
+                    Arc::as_ptr(callback) as *mut c_void,
+                    Some(libarchive_passphrase_callback),
+                ),
+                archive_reader,
+            )?;
+        }
+        for option in &self.format_options {
+            let option = CString::new(option.to_string())
+                .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+            archive_result(
+                ffi::archive_read_set_options(archive_reader, option.as_ptr()),
+                archive_reader,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct PassphraseCallback<'a> {
+    state: Mutex<PassphraseState<'a>>,
+}
+
+struct PassphraseState<'a> {
+    callback: Box<dyn FnMut() -> Option<ArchivePassword> + Send + 'a>,
+    // libarchive copies the passphrase once the callback returns; keep it
+    // alive until then.
+    last: Option<ArchivePassword>,
+}
+
+unsafe extern "C" fn libarchive_passphrase_callback(
+    _: *mut ffi::archive,
+    client_data: *mut c_void,
+) -> *const c_char {
+    let callback = &*(client_data as *const PassphraseCallback);
+    let mut state = callback
+        .state
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    state.last = (state.callback)();
+    state
+        .last
+        .as_ref()
+        .map_or(std::ptr::null(), ArchivePassword::as_ptr)
+}
+
+/// What to do with character and block devices, FIFOs and sockets found
+/// while extracting to disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpecialFilePolicy {
+    /// Create them, as libarchive does. Creating devices requires root.
+    #[default]
+    Allow,
+    /// Leave them out, reporting them as
+    /// [`EntryAction::Skipped`](crate::EntryAction::Skipped).
+    Skip,
+    /// Fail the extraction with [`std::io::ErrorKind::InvalidData`].
+    Error,
+}
+
+/// Settings for extracting an archive to disk with
+/// [`uncompress_archive_with_options`](crate::uncompress_archive_with_options).
+///
+/// The default configuration ignores ownership information stored in the
+/// archive and applies no reader options.
+#[must_use]
+pub struct ExtractOptions<'a> {
+    pub(crate) ownership: Ownership,
+    pub(crate) read: ReadOptions<'a>,
+    pub(crate) verbatim_names: bool,
+    pub(crate) conflict: ConflictPolicy<'a>,
+    pub(crate) links: LinkPolicy,
+    pub(crate) special_files: SpecialFilePolicy,
+    pub(crate) strip_special_bits: bool,
+    pub(crate) owner_mapping: OwnerMapping<'a>,
+    pub(crate) owner_lookup: OwnerLookup,
+    pub(crate) umask: u32,
+    pub(crate) dir_mode: Option<u32>,
+    pub(crate) file_mode: Option<u32>,
+    pub(crate) no_world_writable: bool,
+    pub(crate) sparse: bool,
+}
+
+impl Default for ExtractOptions<'_> {
+    fn default() -> Self {
+        ExtractOptions {
+            ownership: Ownership::Ignore,
+            read: ReadOptions::default(),
+            verbatim_names: false,
+            conflict: ConflictPolicy::default(),
+            links: LinkPolicy::default(),
+            special_files: SpecialFilePolicy::default(),
+            strip_special_bits: false,
+            owner_mapping: OwnerMapping::default(),
+            owner_lookup: OwnerLookup::default(),
+            umask: 0,
+            dir_mode: None,
+            file_mode: None,
+            no_world_writable: false,
+            sparse: false,
+        }
+    }
+}
+
+impl<'a> ExtractOptions<'a> {
+    /// Create the default extraction settings.
+    pub fn new() -> ExtractOptions<'a> {
+        ExtractOptions::default()
+    }
+
+    /// Set whether file ownership stored in the archive is restored.
+    pub fn ownership(mut self, ownership: Ownership) -> ExtractOptions<'a> {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Set the reader options used to open the archive.
+    pub fn read_options(mut self, read: ReadOptions<'a>) -> ExtractOptions<'a> {
+        self.read = read;
+        self
+    }
+
+    /// Create files using the pathname bytes stored in the archive instead
+    /// of the decoded names, so names that are not valid UTF-8 are extracted
+    /// as-is rather than failing. The [`decoder`](ReadOptions::decoder) is not
+    /// consulted in this mode. Only lossless on Unix; elsewhere names are
+    /// converted from UTF-8, replacing invalid sequences.
+    pub fn verbatim_names(mut self, enable: bool) -> ExtractOptions<'a> {
+        self.verbatim_names = enable;
+        self
+    }
+
+    /// Set what happens to entries whose destination path already exists.
+    /// Defaults to [`ConflictPolicy::Overwrite`].
+    pub fn conflict_policy(mut self, policy: ConflictPolicy<'a>) -> ExtractOptions<'a> {
+        self.conflict = policy;
+        self
+    }
+
+    /// Set which symbolic and hard links may be created. Defaults to
+    /// [`LinkPolicy::new`], allowing every link.
+    pub fn link_policy(mut self, policy: LinkPolicy) -> ExtractOptions<'a> {
+        self.links = policy;
+        self
+    }
+
+    /// Set what happens to device nodes, FIFOs and sockets. Defaults to
+    /// [`SpecialFilePolicy::Allow`].
+    pub fn special_files(mut self, policy: SpecialFilePolicy) -> ExtractOptions<'a> {
+        self.special_files = policy;
+        self
+    }
+
+    /// Clear the setuid, setgid and sticky bits of every entry. The removed
+    /// bits are recorded in [`ExtractedEntry::stripped_mode`](crate::ExtractedEntry::stripped_mode).
+    pub fn strip_special_bits(mut self, enable: bool) -> ExtractOptions<'a> {
+        self.strip_special_bits = enable;
+        self
+    }
+
+    /// Set how ownership is translated before being restored. Only used
+    /// with [`Ownership::Preserve`].
+    pub fn owner_mapping(mut self, mapping: OwnerMapping<'a>) -> ExtractOptions<'a> {
+        self.owner_mapping = mapping;
+        self
+    }
+
+    /// Set whether user and group names or numeric ids take precedence.
+    /// Defaults to [`OwnerLookup::Names`].
+    pub fn owner_lookup(mut self, lookup: OwnerLookup) -> ExtractOptions<'a> {
+        self.owner_lookup = lookup;
+        self
+    }
+
+    /// Clear the permission bits set in `umask` from every entry. The
+    /// process umask is not applied, as archive permissions are restored
+    /// verbatim.
+    pub fn umask(mut self, umask: u32) -> ExtractOptions<'a> {
+        self.umask = umask;
+        self
+    }
+
+    /// Create every directory with `mode` instead of its archived
+    /// permissions. The [`umask`](Self::umask) still applies.
+    pub fn dir_mode(mut self, mode: u32) -> ExtractOptions<'a> {
+        self.dir_mode = Some(mode);
+        self
+    }
+
+    /// Create every regular file with `mode` instead of its archived
+    /// permissions. The [`umask`](Self::umask) still applies.
+    pub fn file_mode(mut self, mode: u32) -> ExtractOptions<'a> {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// Never create world-writable entries, clearing the bit where set.
+    pub fn no_world_writable(mut self, enable: bool) -> ExtractOptions<'a> {
+        self.no_world_writable = enable;
+        self
+    }
+
+    /// Create holes in extracted files for runs of zeros, including those
+    /// stored as holes of sparse entries (libarchive's
+    /// `ARCHIVE_EXTRACT_SPARSE`). Only affects
+    /// [`uncompress_archive_with_options`](crate::uncompress_archive_with_options):
+    /// sinks are always handed the holes as zeros.
+    pub fn sparse(mut self, enable: bool) -> ExtractOptions<'a> {
+        self.sparse = enable;
+        self
+    }
+
+    /// The permissions to create an entry of `filetype` archived with
+    /// `perm` with.
+    pub(crate) fn mode(&self, perm: u32, filetype: u32) -> u32 {
+        let forced = match filetype {
+            ffi::AE_IFDIR => self.dir_mode,
+            ffi::AE_IFREG => self.file_mode,
+            _ => None,
+        };
+        let mut mode = forced.unwrap_or(perm) & !self.umask;
+        if self.no_world_writable {
+            mode &= !0o002;
+        }
+        mode
+    }
+
+    /// Flags for libarchive's disk writer.
+    pub(crate) fn writer_flags(&self) -> u32 {
+        let mut flags = DISK_WRITER_FLAGS;
+        if let Ownership::Preserve = self.ownership {
+            flags |= ffi::ARCHIVE_EXTRACT_OWNER;
+        }
+        if self.links.secure_symlinks_enabled() {
+            flags |= ffi::ARCHIVE_EXTRACT_SECURE_SYMLINKS;
+        }
+        if self.sparse {
+            flags |= ffi::ARCHIVE_EXTRACT_SPARSE;
+        }
+        flags
+    }
+}
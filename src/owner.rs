@@ -0,0 +1,198 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Remapping of entry ownership, e.g. into the subordinate ids of a rootless
+//! container.
+
+use crate::{ffi, Result};
+use std::{ffi::CStr, fmt, io, os::raw::c_char};
+
+// Largest id of `uid_t` and `gid_t`, to which libarchive truncates ids when
+// changing ownership.
+const MAX_ID: u64 = u32::MAX as u64;
+
+/// The callback type of [`OwnerMapping::Callback`].
+pub type OwnerCallback<'a> =
+    dyn Fn(u64, u64, Option<&str>, Option<&str>) -> (u64, u64) + Send + Sync + 'a;
+
+/// A contiguous range of ids mapped to another one, like a line of
+/// `/etc/subuid` or `/proc/<pid>/uid_map`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdRange {
+    /// First id of the range, as stored in the archive.
+    pub inside: u64,
+    /// First id on the host it is mapped to.
+    pub outside: u64,
+    /// Number of ids in the range.
+    pub count: u64,
+}
+
+impl IdRange {
+    pub fn new(inside: u64, outside: u64, count: u64) -> IdRange {
+        IdRange {
+            inside,
+            outside,
+            count,
+        }
+    }
+
+    /// The id `id` is mapped to, or `None` if outside the range. Ids past
+    /// `u64::MAX` saturate, to be rejected with every id above `u32::MAX`.
+    fn map(&self, id: u64) -> Option<u64> {
+        let offset = id.checked_sub(self.inside)?;
+        (offset < self.count).then(|| self.outside.saturating_add(offset))
+    }
+}
+
+/// How entry ownership is translated before being applied. Only used with
+/// [`Ownership::Preserve`](crate::Ownership::Preserve).
+///
+/// Whatever the mapping, resulting ids above `u32::MAX`, the largest `uid_t`
+/// and `gid_t`, fail the extraction with
+/// [`std::io::ErrorKind::InvalidData`].
+///
+/// # Example
+///
+/// Unpacking a container layer for a user whose subordinate ids start at
+/// 100000:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+/// use std::path::Path;
+///
+/// let mut source = File::open("layer.tar")?;
+/// let options = ExtractOptions::new()
+///     .ownership(Ownership::Preserve)
+///     .owner_lookup(OwnerLookup::Ids)
+///     .owner_mapping(OwnerMapping::Ranges {
+///         uids: vec![IdRange::new(0, 100000, 65536)],
+///         gids: vec![IdRange::new(0, 100000, 65536)],
+///     });
+///
+/// uncompress_archive_with_options(&mut source, Path::new("/tmp/rootfs"), options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+#[non_exhaustive]
+pub enum OwnerMapping<'a> {
+    /// Apply the ownership as found.
+    #[default]
+    Identity,
+    /// Give every entry the same owner.
+    Fixed { uid: u64, gid: u64 },
+    /// Translate ids through ranges. Ids outside of every range fail the
+    /// extraction with [`std::io::ErrorKind::InvalidData`].
+    Ranges {
+        uids: Vec<IdRange>,
+        gids: Vec<IdRange>,
+    },
+    /// Let a callback compute the owner from the uid, gid, user name and
+    /// group name of the entry.
+    Callback(Box<OwnerCallback<'a>>),
+}
+
+impl fmt::Debug for OwnerMapping<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OwnerMapping::Identity => f.write_str("Identity"),
+            OwnerMapping::Fixed { uid, gid } => f
+                .debug_struct("Fixed")
+                .field("uid", uid)
+                .field("gid", gid)
+                .finish(),
+            OwnerMapping::Ranges { uids, gids } => f
+                .debug_struct("Ranges")
+                .field("uids", uids)
+                .field("gids", gids)
+                .finish(),
+            OwnerMapping::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Whether user and group names stored in the archive take precedence over
+/// numeric ids when restoring ownership.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OwnerLookup {
+    /// Resolve names against the local user database, falling back to the
+    /// ids for unknown names, like tar.
+    #[default]
+    Names,
+    /// Use the numeric ids only, like tar's `--numeric-owner`.
+    Ids,
+}
+
+impl OwnerMapping<'_> {
+    /// Rewrite the ownership of `entry`, resolving names through the lookup
+    /// of `archive_writer` first.
+    pub(crate) unsafe fn apply(
+        &self,
+        archive_writer: *mut ffi::archive,
+        entry: *mut ffi::archive_entry,
+    ) -> Result<()> {
+        if let OwnerMapping::Identity = self {
+            return Ok(());
+        }
+
+        let uname = ffi::archive_entry_uname(entry);
+        let gname = ffi::archive_entry_gname(entry);
+        let uid = ffi::archive_write_disk_uid(archive_writer, uname, ffi::archive_entry_uid(entry));
+        let gid = ffi::archive_write_disk_gid(archive_writer, gname, ffi::archive_entry_gid(entry));
+        let (uid, gid) = (uid.max(0) as u64, gid.max(0) as u64);
+
+        let (uid, gid) = match self {
+            OwnerMapping::Identity => (uid, gid),
+            OwnerMapping::Fixed { uid, gid } => (*uid, *gid),
+            OwnerMapping::Ranges { uids, gids } => {
+                (map_id(uids, uid, "uid")?, map_id(gids, gid, "gid")?)
+            }
+            OwnerMapping::Callback(callback) => {
+                callback(uid, gid, borrowed_name(uname), borrowed_name(gname))
+            }
+        };
+        let (uid, gid) = (checked_id(uid, "uid")?, checked_id(gid, "gid")?);
+
+        ffi::archive_entry_set_uid(entry, uid as ffi::la_int64_t);
+        ffi::archive_entry_set_gid(entry, gid as ffi::la_int64_t);
+        // Names would be looked up again when writing the entry.
+        ffi::archive_entry_set_uname(entry, std::ptr::null());
+        ffi::archive_entry_set_gname(entry, std::ptr::null());
+        Ok(())
+    }
+}
+
+fn map_id(ranges: &[IdRange], id: u64, kind: &str) -> Result<u64> {
+    ranges
+        .iter()
+        .find_map(|range| range.map(id))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} {} is not mapped", kind, id),
+            )
+            .into()
+        })
+}
+
+fn checked_id(id: u64, kind: &str) -> Result<u64> {
+    if id > MAX_ID {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} {} is out of range", kind, id),
+        )
+        .into());
+    }
+    Ok(id)
+}
+
+unsafe fn borrowed_name<'a>(name: *const c_char) -> Option<&'a str> {
+    if name.is_null() {
+        return None;
+    }
+    CStr::from_ptr(name).to_str().ok()
+}
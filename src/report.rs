@@ -0,0 +1,69 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! What happened to each entry during an extraction to disk.
+
+use std::path::{Path, PathBuf};
+
+/// How an entry was written to the destination.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntryAction {
+    /// Nothing existed at the entry path.
+    Created,
+    /// The directory already existed; its metadata was updated.
+    Merged,
+    /// An existing file, or one extracted earlier from the same archive, was
+    /// replaced.
+    Overwritten,
+    /// The entry was not extracted: either an existing file was kept, or
+    /// the entry was left out by
+    /// [`LinkPolicy::skip_links`](crate::LinkPolicy::skip_links) or
+    /// [`SpecialFilePolicy::Skip`](crate::SpecialFilePolicy::Skip).
+    Skipped,
+    /// An existing file was kept and the entry extracted to the given path
+    /// instead, relative to the destination.
+    Renamed(PathBuf),
+}
+
+/// An entry handled by
+/// [`uncompress_archive_with_options`](crate::uncompress_archive_with_options).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExtractedEntry {
+    /// Path of the entry relative to the destination, after sanitization.
+    pub path: PathBuf,
+    pub action: EntryAction,
+    /// The setuid, setgid and sticky bits removed from the entry's mode by
+    /// [`ExtractOptions::strip_special_bits`](crate::ExtractOptions::strip_special_bits).
+    pub stripped_mode: u32,
+}
+
+/// The entries extracted by
+/// [`uncompress_archive_with_options`](crate::uncompress_archive_with_options),
+/// in archive order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExtractReport {
+    pub entries: Vec<ExtractedEntry>,
+}
+
+impl ExtractReport {
+    /// The action taken for the entry at `path`, if it was extracted.
+    pub fn action<P: AsRef<Path>>(&self, path: P) -> Option<&EntryAction> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.path == path.as_ref())
+            .map(|entry| &entry.action)
+    }
+
+    pub(crate) fn push(&mut self, path: PathBuf, action: EntryAction, stripped_mode: u32) {
+        self.entries.push(ExtractedEntry {
+            path,
+            action,
+            stripped_mode,
+        });
+    }
+}
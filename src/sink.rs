@@ -0,0 +1,378 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Extraction into destinations other than libarchive's disk writer, e.g.
+//! object storage, an in-memory tree or a container layer.
+
+use crate::{
+    apply_entry_options, apply_owner_lookup, error::archive_result, ffi, libarchive_entry_mtime,
+    libarchive_entry_pathname, libarchive_entry_symlink, libarchive_write_data_block,
+    links::ExtractedSymlinks, os_string_from_bytes, run_with_archive, sanitize_destination_path,
+    ExtractOptions, Ownership, Result, DISK_WRITER_FLAGS,
+};
+#[cfg(unix)]
+use std::sync::Arc;
+use std::{
+    ffi::CStr,
+    fs,
+    io::{Read, Seek, Write},
+    os::raw::c_char,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Metadata of an archive entry handed to an [`ExtractSink`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EntryMetadata {
+    /// Permission bits, including the setuid, setgid and sticky bits.
+    pub mode: u32,
+    /// Modification time, if the archive records one.
+    pub mtime: Option<SystemTime>,
+    pub uid: u64,
+    pub gid: u64,
+    pub uname: Option<String>,
+    pub gname: Option<String>,
+    /// Size of the entry data in bytes; 0 for directories and links.
+    pub size: u64,
+}
+
+impl EntryMetadata {
+    pub(crate) unsafe fn from_entry(entry: *mut ffi::archive_entry) -> EntryMetadata {
+        EntryMetadata {
+            mode: ffi::archive_entry_perm(entry) as u32,
+            mtime: (ffi::archive_entry_mtime_is_set(entry) != 0)
+                .then(|| libarchive_entry_mtime(entry)),
+            uid: ffi::archive_entry_uid(entry).max(0) as u64,
+            gid: ffi::archive_entry_gid(entry).max(0) as u64,
+            uname: owned_name(ffi::archive_entry_uname(entry)),
+            gname: owned_name(ffi::archive_entry_gname(entry)),
+            size: ffi::archive_entry_size(entry).max(0) as u64,
+        }
+    }
+}
+
+unsafe fn owned_name(name: *const c_char) -> Option<String> {
+    if name.is_null() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(CStr::from_ptr(name).to_bytes()).into_owned())
+}
+
+/// A destination for [`uncompress_archive_into`](crate::uncompress_archive_into).
+///
+/// Paths are relative to the root of the destination and have been through
+/// the same sanitization as [`uncompress_archive`](crate::uncompress_archive):
+/// leading `/` stripped, `..` components rejected. Symlink targets are passed
+/// verbatim, as stored in the archive.
+///
+/// Entries are handed over in archive order. Regular files are followed by a
+/// [`set_metadata`](ExtractSink::set_metadata) call once written; for
+/// directories, it is deferred until every entry is extracted and made for
+/// children before their parents, so read-only directories can be filled
+/// and keep their modification time. Device nodes, FIFOs and sockets are
+/// skipped.
+pub trait ExtractSink {
+    /// Writer receiving the content of a regular file.
+    type File<'a>: Write
+    where
+        Self: 'a;
+
+    /// Create the directory `path`.
+    fn create_dir(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()>;
+
+    /// Create the regular file `path`, returning the writer its content is
+    /// written to.
+    fn create_file(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<Self::File<'_>>;
+
+    /// Create `path` as a symbolic link pointing at `target`.
+    fn symlink(&mut self, path: &Path, target: &Path, metadata: &EntryMetadata) -> Result<()>;
+
+    /// Create `path` as a hard link to the previously extracted `target`.
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()>;
+
+    /// Apply `metadata` to the directory or file at `path`.
+    fn set_metadata(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()>;
+}
+
+/// An [`ExtractSink`] writing to a directory on the local disk.
+///
+/// No entry is ever written through a symbolic link, including one
+/// extracted earlier from the same archive: on Unix every path is resolved
+/// below `dest` as [`DirFdSink`](crate::DirFdSink) does, elsewhere each
+/// parent directory is checked not to be a link before it is used. Existing
+/// files and links in the way of an entry are replaced.
+///
+/// Permissions and modification times are applied; ownership only with
+/// [`Ownership::Preserve`] on Unix, by numeric id.
+///
+/// On Unix, `dest` is created and opened on first use, and the handle kept
+/// for the lifetime of the sink and its clones.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let mut source = File::open("tree.tar.gz")?;
+/// let mut sink = DiskSink::new("/tmp/dest");
+///
+/// uncompress_archive_into(&mut source, &mut sink)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct DiskSink {
+    dest: PathBuf,
+    ownership: Ownership,
+    #[cfg(unix)]
+    dir: Option<Arc<fs::File>>,
+}
+
+impl DiskSink {
+    /// Create a sink extracting below `dest`, ignoring ownership.
+    pub fn new<P: Into<PathBuf>>(dest: P) -> DiskSink {
+        DiskSink {
+            dest: dest.into(),
+            ownership: Ownership::Ignore,
+            #[cfg(unix)]
+            dir: None,
+        }
+    }
+
+    /// Set whether ownership stored in the archive is applied.
+    pub fn ownership(mut self, ownership: Ownership) -> DiskSink {
+        self.ownership = ownership;
+        self
+    }
+
+    /// Run `f` on a [`DirFdSink`](crate::DirFdSink) for `dest`, creating
+    /// and opening it on first use.
+    #[cfg(unix)]
+    fn at<T>(&mut self, f: impl FnOnce(&mut crate::DirFdSink<'_>) -> Result<T>) -> Result<T> {
+        use std::os::fd::AsFd;
+
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => {
+                fs::create_dir_all(&self.dest)?;
+                let dir = Arc::new(fs::File::open(&self.dest)?);
+                self.dir = Some(dir.clone());
+                dir
+            }
+        };
+        f(&mut crate::DirFdSink::new(dir.as_fd()).ownership(self.ownership))
+    }
+
+    /// Create the missing parents of `path` below `dest`, failing if one of
+    /// them is not a directory, e.g. a symbolic link.
+    #[cfg(not(unix))]
+    fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        let mut parent = self.dest.clone();
+        fs::create_dir_all(&parent)?;
+        for component in path.parent().into_iter().flat_map(Path::components) {
+            parent.push(component);
+            match fs::symlink_metadata(&parent) {
+                Ok(metadata) if metadata.is_dir() => {}
+                Ok(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} is not a directory", parent.display()),
+                    )
+                    .into())
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => fs::create_dir(&parent)?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(self.dest.join(path))
+    }
+
+    /// Like [`resolve`](Self::resolve), also removing whatever is at `path`
+    /// but a directory, so it can be created anew.
+    #[cfg(not(unix))]
+    fn replace(&self, path: &Path) -> Result<PathBuf> {
+        let path = self.resolve(path)?;
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if !metadata.is_dir() => fs::remove_file(&path)?,
+            _ => {}
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(unix)]
+impl ExtractSink for DiskSink {
+    type File<'a> = fs::File;
+
+    fn create_dir(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.at(|sink| sink.create_dir(path, metadata))
+    }
+
+    fn create_file(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<fs::File> {
+        self.at(|sink| sink.create_file(path, metadata))
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.at(|sink| sink.symlink(path, target, metadata))
+    }
+
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        self.at(|sink| sink.hardlink(path, target))
+    }
+
+    fn set_metadata(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.at(|sink| sink.set_metadata(path, metadata))
+    }
+}
+
+#[cfg(not(unix))]
+impl ExtractSink for DiskSink {
+    type File<'a> = fs::File;
+
+    fn create_dir(&mut self, path: &Path, _: &EntryMetadata) -> Result<()> {
+        let path = self.replace(path)?;
+        if !path.is_dir() {
+            fs::create_dir(path)?;
+        }
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path, _: &EntryMetadata) -> Result<fs::File> {
+        Ok(fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(self.replace(path)?)?)
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path, _: &EntryMetadata) -> Result<()> {
+        std::os::windows::fs::symlink_file(target, self.replace(path)?)?;
+        Ok(())
+    }
+
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let target = self.resolve(target)?;
+        fs::hard_link(target, self.replace(path)?)?;
+        Ok(())
+    }
+
+    fn set_metadata(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        let path = self.resolve(path)?;
+        if let Some(mtime) = metadata.mtime {
+            // Directories cannot be opened as files on Windows.
+            if !path.is_dir() {
+                fs::File::open(&path)?.set_modified(mtime)?;
+            }
+        }
+        let mut permissions = fs::metadata(&path)?.permissions();
+        permissions.set_readonly(metadata.mode & 0o222 == 0);
+        fs::set_permissions(&path, permissions)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn extract_into<R, S>(
+    mut source: R,
+    sink: &mut S,
+    options: &ExtractOptions,
+) -> Result<()>
+where
+    R: Read + Seek,
+    S: ExtractSink,
+{
+    let _utf8_guard = ffi::UTF8LocaleGuard::new();
+    let resolved = options.read.resolve(&mut source);
+    let read_options = &*resolved;
+    let entry_path = |bytes: &[u8]| -> Result<PathBuf> {
+        if options.verbatim_names {
+            Ok(PathBuf::from(os_string_from_bytes(bytes.to_vec())))
+        } else {
+            Ok(PathBuf::from(read_options.decode(bytes)?))
+        }
+    };
+    let sanitized_path = |bytes: &[u8]| -> Result<PathBuf> {
+        // Also drops trailing separators and `.` components.
+        Ok(sanitize_destination_path(&entry_path(bytes)?)?
+            .components()
+            .collect())
+    };
+    run_with_archive(
+        DISK_WRITER_FLAGS,
+        read_options,
+        source,
+        |archive_reader, archive_writer, mut entry| unsafe {
+            apply_owner_lookup(options, archive_writer)?;
+            // Applied once all entries are written, so read-only directories
+            // can be filled and their mtime is not changed by their children.
+            let mut directories = Vec::new();
+            let mut symlinks = ExtractedSymlinks::default();
+            loop {
+                match ffi::archive_read_next_header(archive_reader, &mut entry) {
+                    ffi::ARCHIVE_EOF => break,
+                    value => archive_result(value, archive_reader)?,
+                }
+
+                let _utf8_guard = ffi::WindowsUTF8LocaleGuard::new();
+                if !read_options.selects(entry)? {
+                    continue;
+                }
+                let path = sanitized_path(libarchive_entry_pathname(entry)?.to_bytes())?;
+                if apply_entry_options(
+                    options,
+                    archive_writer,
+                    entry,
+                    &path,
+                    &symlinks,
+                    entry_path,
+                )?
+                .is_none()
+                {
+                    continue;
+                }
+                let metadata = EntryMetadata::from_entry(entry);
+
+                let hardlink = ffi::archive_entry_hardlink(entry);
+                if !hardlink.is_null() {
+                    let link = sanitized_path(CStr::from_ptr(hardlink).to_bytes())?;
+                    symlinks.record_hardlink(&path, &link);
+                    sink.hardlink(&path, &link)?;
+                    continue;
+                }
+                let target = libarchive_entry_symlink(entry, entry_path)?;
+                symlinks.record(&path, target.clone());
+
+                match ffi::archive_entry_filetype(entry) as u32 {
+                    ffi::AE_IFDIR => {
+                        sink.create_dir(&path, &metadata)?;
+                        directories.push((path, metadata));
+                        continue;
+                    }
+                    ffi::AE_IFREG => {
+                        let mut file = sink.create_file(&path, &metadata)?;
+                        libarchive_write_data_block(archive_reader, &mut file)?;
+                        file.flush()?;
+                    }
+                    ffi::AE_IFLNK => {
+                        if let Some(target) = target {
+                            sink.symlink(&path, &target, &metadata)?;
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                }
+                sink.set_metadata(&path, &metadata)?;
+            }
+
+            // Children sort after their parents.
+            directories.sort_by(|(a, _), (b, _)| b.cmp(a));
+            for (path, metadata) in directories {
+                sink.set_metadata(&path, &metadata)?;
+            }
+            Ok(())
+        },
+    )
+}
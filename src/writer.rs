@@ -0,0 +1,344 @@
+// Copyright (C) 2026 O.S. Systems Software LTDA
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Archive creation, including password protected ZIP archives.
+
+use crate::{
+    error::{archive_result, archive_result_strict},
+    ffi, ArchivePassword, Error, Result, READER_BUFFER_SIZE,
+};
+use std::{
+    ffi::CString,
+    fmt,
+    io::{self, Read, Write},
+    os::raw::c_void,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The format of an archive created by [`ArchiveWriter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    /// ZIP, the only format supporting encryption.
+    Zip,
+    /// POSIX tar, using pax extensions only where needed.
+    Tar,
+    /// 7-Zip.
+    SevenZip,
+}
+
+/// How entries of a password protected ZIP archive are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncryptionMethod {
+    /// WinZip AES with a 256-bit key.
+    Aes256,
+    /// WinZip AES with a 128-bit key.
+    Aes128,
+    /// Traditional PKWARE (ZipCrypto) encryption. Weak; only use it when the
+    /// archive has to be opened by tools lacking AES support.
+    Traditional,
+}
+
+impl fmt::Display for EncryptionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionMethod::Aes256 => f.write_str("zip:encryption=aes256"),
+            EncryptionMethod::Aes128 => f.write_str("zip:encryption=aes128"),
+            EncryptionMethod::Traditional => f.write_str("zip:encryption=traditional"),
+        }
+    }
+}
+
+struct WriterPipe<W> {
+    writer: W,
+}
+
+/// A builder to create an [`ArchiveWriter`] writing to `target`.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use compress_tools::*;
+/// use std::fs::File;
+///
+/// let target = File::create("deliverable.zip")?;
+/// let mut writer = ArchiveWriterBuilder::new(target, ArchiveFormat::Zip)
+///     .with_password(ArchivePassword::new("secret")?)
+///     .encryption(EncryptionMethod::Aes256)
+///     .build()?;
+///
+/// let report = b"quarterly numbers";
+/// writer.append_directory("reports/")?;
+/// writer.append_file("reports/q1.txt", &report[..], report.len() as u64)?;
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub struct ArchiveWriterBuilder<W>
+where
+    W: Write,
+{
+    target: W,
+    format: ArchiveFormat,
+    password: Option<ArchivePassword>,
+    encryption: Option<EncryptionMethod>,
+}
+
+impl<W> ArchiveWriterBuilder<W>
+where
+    W: Write,
+{
+    /// Create a new builder for an archive in `format`, written to `target`.
+    pub fn new(target: W, format: ArchiveFormat) -> ArchiveWriterBuilder<W> {
+        ArchiveWriterBuilder {
+            target,
+            format,
+            password: None,
+            encryption: None,
+        }
+    }
+
+    /// Encrypt every entry with `password`. Unless another method is chosen
+    /// with [`encryption`](Self::encryption), AES-256 is used.
+    pub fn with_password(mut self, password: ArchivePassword) -> ArchiveWriterBuilder<W> {
+        self.password = Some(password);
+        self
+    }
+
+    /// Choose how entries are encrypted. Requires a password.
+    pub fn encryption(mut self, method: EncryptionMethod) -> ArchiveWriterBuilder<W> {
+        self.encryption = Some(method);
+        self
+    }
+
+    /// Finish the builder and start writing the archive.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if an encryption
+    /// method is set without a password, or a password is set for a format
+    /// other than ZIP.
+    pub fn build(self) -> Result<ArchiveWriter<W>> {
+        let encryption = match (&self.password, self.encryption) {
+            (None, None) => None,
+            (None, Some(_)) => return Err(invalid_input("an encryption method needs a password")),
+            (Some(_), _) if self.format != ArchiveFormat::Zip => {
+                return Err(invalid_input("only ZIP archives can be encrypted"))
+            }
+            (Some(_), method) => Some(method.unwrap_or(EncryptionMethod::Aes256)),
+        };
+
+        let _utf8_guard = ffi::UTF8LocaleGuard::new();
+        let mut pipe = Box::new(WriterPipe {
+            writer: self.target,
+        });
+        let client_data = std::ptr::addr_of_mut!(*pipe) as *mut c_void;
+
+        unsafe {
+            let archive_writer = ffi::archive_write_new();
+            if archive_writer.is_null() {
+                return Err(Error::NullArchive);
+            }
+            // Frees the archive on early returns below.
+            let writer = ArchiveWriter {
+                archive_writer,
+                pipe: Some(pipe),
+            };
+
+            let set_format = match self.format {
+                ArchiveFormat::Zip => ffi::archive_write_set_format_zip,
+                ArchiveFormat::Tar => ffi::archive_write_set_format_pax_restricted,
+                ArchiveFormat::SevenZip => ffi::archive_write_set_format_7zip,
+            };
+            archive_result(set_format(archive_writer), archive_writer)?;
+            // Do not pad the output to a whole block.
+            archive_result(
+                ffi::archive_write_set_bytes_in_last_block(archive_writer, 1),
+                archive_writer,
+            )?;
+
+            if let (Some(password), Some(method)) = (&self.password, encryption) {
+                let option = CString::new(method.to_string()).unwrap();
+                archive_result(
+                    ffi::archive_write_set_options(archive_writer, option.as_ptr()),
+                    archive_writer,
+                )?;
+                archive_result(
+                    ffi::archive_write_set_passphrase(archive_writer, password.as_ptr()),
+                    archive_writer,
+                )?;
+            }
+
+            archive_result(
+                ffi::archive_write_open(
+                    archive_writer,
+                    client_data,
+                    None,
+                    Some(libarchive_write_callback::<W>),
+                    None,
+                ),
+                archive_writer,
+            )?;
+
+            Ok(writer)
+        }
+    }
+}
+
+/// Writes entries into a new archive. Create one with
+/// [`ArchiveWriterBuilder`].
+///
+/// Call [`finish`](ArchiveWriter::finish) once all entries are added; the
+/// archive is also completed on drop, but errors are lost then.
+pub struct ArchiveWriter<W>
+where
+    W: Write,
+{
+    archive_writer: *mut ffi::archive,
+    pipe: Option<Box<WriterPipe<W>>>,
+}
+
+impl<W> ArchiveWriter<W>
+where
+    W: Write,
+{
+    /// Add a regular file at `path`, reading exactly `size` bytes of content
+    /// from `data`.
+    pub fn append_file<D>(&mut self, path: &str, mut data: D, size: u64) -> Result<()>
+    where
+        D: Read,
+    {
+        let _utf8_guard = ffi::UTF8LocaleGuard::new();
+        unsafe {
+            self.write_header(path, ffi::AE_IFREG, 0o644, size)?;
+
+            let mut buffer = [0; READER_BUFFER_SIZE];
+            let mut written = 0;
+            loop {
+                let read = match data.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                written += read as u64;
+                if written > size {
+                    break;
+                }
+                if ffi::archive_write_data(
+                    self.archive_writer,
+                    buffer.as_ptr() as *const c_void,
+                    read,
+                ) < 0
+                {
+                    return Err(Error::from(self.archive_writer));
+                }
+            }
+            if written != size {
+                return Err(invalid_input(format!(
+                    "entry '{}' declared {} bytes but {} were provided",
+                    path, size, written
+                )));
+            }
+
+            archive_result_strict(
+                ffi::archive_write_finish_entry(self.archive_writer),
+                self.archive_writer,
+            )
+        }
+    }
+
+    /// Add a directory at `path`.
+    pub fn append_directory(&mut self, path: &str) -> Result<()> {
+        let _utf8_guard = ffi::UTF8LocaleGuard::new();
+        unsafe {
+            self.write_header(path, ffi::AE_IFDIR, 0o755, 0)?;
+            archive_result_strict(
+                ffi::archive_write_finish_entry(self.archive_writer),
+                self.archive_writer,
+            )
+        }
+    }
+
+    /// Complete the archive and return the target writer.
+    pub fn finish(mut self) -> Result<W> {
+        let _utf8_guard = ffi::UTF8LocaleGuard::new();
+        unsafe {
+            let archive_writer = std::mem::replace(&mut self.archive_writer, std::ptr::null_mut());
+            let closed = archive_result(ffi::archive_write_close(archive_writer), archive_writer);
+            archive_result(ffi::archive_write_free(archive_writer), archive_writer)?;
+            closed?;
+        }
+        Ok(self.pipe.take().unwrap().writer)
+    }
+
+    unsafe fn write_header(
+        &mut self,
+        path: &str,
+        filetype: u32,
+        perm: u32,
+        size: u64,
+    ) -> Result<()> {
+        let pathname = CString::new(path)
+            .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+        let mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let entry = ffi::archive_entry_new();
+        ffi::archive_entry_set_pathname_utf8(entry, pathname.as_ptr());
+        ffi::archive_entry_set_filetype(entry, filetype);
+        ffi::archive_entry_set_perm(entry, perm as _);
+        ffi::archive_entry_set_size(entry, size as ffi::la_int64_t);
+        ffi::archive_entry_set_mtime(entry, mtime as _, 0);
+        let res = archive_result_strict(
+            ffi::archive_write_header(self.archive_writer, entry),
+            self.archive_writer,
+        );
+        ffi::archive_entry_free(entry);
+        res
+    }
+}
+
+impl<W> Drop for ArchiveWriter<W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        if !self.archive_writer.is_null() {
+            unsafe {
+                ffi::archive_write_free(self.archive_writer);
+            }
+        }
+    }
+}
+
+fn invalid_input<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Error::Io(io::Error::new(io::ErrorKind::InvalidInput, error))
+}
+
+unsafe extern "C" fn libarchive_write_callback<W: Write>(
+    archive: *mut ffi::archive,
+    client_data: *mut c_void,
+    buffer: *const c_void,
+    length: usize,
+) -> ffi::la_ssize_t {
+    let pipe = (client_data as *mut WriterPipe<W>).as_mut().unwrap();
+    let buffer = std::slice::from_raw_parts(buffer as *const u8, length);
+
+    match pipe.writer.write_all(buffer) {
+        Ok(()) => length as ffi::la_ssize_t,
+        Err(e) => {
+            let description = CString::new(e.to_string()).unwrap();
+
+            ffi::archive_set_error(archive, e.raw_os_error().unwrap_or(0), description.as_ptr());
+
+            -1
+        }
+    }
+}
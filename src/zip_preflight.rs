@@ -5,6 +5,10 @@
 //! ZIP central-directory preflight for compression methods libarchive cannot
 //! decode (currently Deflate64 / method 9). Non-ZIP inputs, malformed ZIPs,
 //! and unseekable readers fall through so libarchive keeps its normal behavior.
+//!
+//! The same walk also exposes the raw entry names, the general-purpose UTF-8
+//! flag and the Info-ZIP Unicode Path extra field, which filename charset
+//! detection relies on, and the encryption scheme of each entry.
 
 use crate::{Error, Result};
 use std::io::{self, Read, Seek, SeekFrom};
@@ -22,21 +26,88 @@ const ZIP64_EOCD_SIZE: usize = 56;
 const MAX_EOCD_COMMENT: u64 = 65_535;
 
 const METHOD_DEFLATE64: u16 = 9;
+const METHOD_AES: u16 = 99;
+
+const FLAG_ENCRYPTED: u16 = 1 << 0;
+const FLAG_STRONG_ENCRYPTION: u16 = 1 << 6;
+const EXTRA_AES: u16 = 0x9901;
+
+#[cfg_attr(not(feature = "charset_detection"), allow(dead_code))]
+const FLAG_UTF8: u16 = 1 << 11;
+const EXTRA_UNICODE_PATH: u16 = 0x7075;
+const EXTRA_ZIP64: u16 = 0x0001;
 
 // Cap CD allocation: the size comes from the archive itself, so a malformed
 // or hostile file could otherwise request arbitrary memory.
 const MAX_CD_SIZE: u64 = 16 * 1024 * 1024;
 
-fn is_unsupported_method(method: u16) -> bool {
+pub(crate) fn is_unsupported_method(method: u16) -> bool {
     // Encryption (method 99) is intentionally not flagged: libarchive can
-    // decrypt via `archive_read_add_passphrase`, and issue #82 tracks
-    // exposing that from this crate.
+    // decrypt it given a password, see `ReadOptions::password`.
     method == METHOD_DEFLATE64
 }
 
+/// How a ZIP entry is encrypted, as declared in its central-directory
+/// header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZipEncryption {
+    /// The entry is stored in the clear.
+    None,
+    /// Traditional PKWARE (ZipCrypto) encryption. Weak, but widely
+    /// supported.
+    Traditional,
+    /// WinZip AES encryption (compression method 99).
+    Aes {
+        /// Key size in bits: 128, 192 or 256, or 0 when the AES extra field
+        /// is missing or malformed.
+        key_bits: u16,
+    },
+    /// PKWARE Strong Encryption, which libarchive cannot decrypt.
+    Strong,
+}
+
+/// The encryption scheme of a single ZIP entry, as reported by
+/// [`list_zip_encryption`](crate::list_zip_encryption).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZipEntryEncryption {
+    pub path: String,
+    pub encryption: ZipEncryption,
+}
+
+/// A central-directory file header, reduced to what the crate inspects.
+#[cfg_attr(not(feature = "charset_detection"), allow(dead_code))]
+pub(crate) struct CdEntry {
+    pub(crate) name: Vec<u8>,
+    pub(crate) method: u16,
+    pub(crate) flags: u16,
+    /// Name from a valid Info-ZIP Unicode Path extra field (0x7075), i.e. one
+    /// whose CRC-32 matches the header name it overrides.
+    pub(crate) unicode_path: Option<String>,
+    pub(crate) encryption: ZipEncryption,
+    /// Offset of the entry's local file header from the start of the file.
+    pub(crate) offset: u64,
+}
+
+#[cfg_attr(not(feature = "charset_detection"), allow(dead_code))]
+impl CdEntry {
+    /// Whether general-purpose bit 11 declares the name as UTF-8.
+    pub(crate) fn is_utf8(&self) -> bool {
+        self.flags & FLAG_UTF8 != 0
+    }
+}
+
 pub(crate) fn reject_unsupported_zip_methods<R: Read + Seek>(reader: &mut R) -> Result<()> {
-    let offending = scan(reader).unwrap_or_default();
-    let _ = reader.seek(SeekFrom::Start(0));
+    let offending: Vec<(String, u16)> = central_directory(reader)
+        .into_iter()
+        .filter(|entry| is_unsupported_method(entry.method))
+        .map(|entry| {
+            (
+                String::from_utf8_lossy(&entry.name).into_owned(),
+                entry.method,
+            )
+        })
+        .collect();
     if offending.is_empty() {
         Ok(())
     } else {
@@ -44,7 +115,15 @@ pub(crate) fn reject_unsupported_zip_methods<R: Read + Seek>(reader: &mut R) ->
     }
 }
 
-fn scan<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<(String, u16)>> {
+/// Read the central directory of a ZIP archive. Returns an empty list for
+/// anything that is not a well-formed ZIP; the reader is rewound either way.
+pub(crate) fn central_directory<R: Read + Seek>(reader: &mut R) -> Vec<CdEntry> {
+    let entries = scan(reader).unwrap_or_default();
+    let _ = reader.seek(SeekFrom::Start(0));
+    entries
+}
+
+fn scan<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<CdEntry>> {
     reader.seek(SeekFrom::Start(0))?;
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic)?;
@@ -121,7 +200,7 @@ fn find_cd<R: Read + Seek>(reader: &mut R, total_len: u64) -> io::Result<Option<
 fn walk_central_directory<R: Read + Seek>(
     reader: &mut R,
     cd: CdLocation,
-) -> io::Result<Vec<(String, u16)>> {
+) -> io::Result<Vec<CdEntry>> {
     if cd.size > MAX_CD_SIZE {
         return Ok(Vec::new());
     }
@@ -129,30 +208,108 @@ fn walk_central_directory<R: Read + Seek>(
     let mut buf = vec![0u8; cd.size as usize];
     reader.read_exact(&mut buf)?;
 
-    let mut offenders = Vec::new();
+    let mut entries = Vec::new();
     let mut pos = 0usize;
     for _ in 0..cd.entries {
         if pos + CD_FILE_HEADER_SIZE > buf.len() || read_u32(&buf, pos) != CD_FILE_HEADER_SIG {
             break;
         }
+        let flags = read_u16(&buf, pos + 8);
         let method = read_u16(&buf, pos + 10);
         let name_len = read_u16(&buf, pos + 28) as usize;
         let extra_len = read_u16(&buf, pos + 30) as usize;
         let comment_len = read_u16(&buf, pos + 32) as usize;
+        let compressed_size = read_u32(&buf, pos + 20);
+        let uncompressed_size = read_u32(&buf, pos + 24);
+        let offset = read_u32(&buf, pos + 42);
         let name_start = pos + CD_FILE_HEADER_SIZE;
         let name_end = name_start + name_len;
         if name_end > buf.len() {
             break;
         }
-        if is_unsupported_method(method) {
-            offenders.push((
-                String::from_utf8_lossy(&buf[name_start..name_end]).into_owned(),
-                method,
-            ));
-        }
+        let name = &buf[name_start..name_end];
+        let extra = &buf[name_end..(name_end + extra_len).min(buf.len())];
+        entries.push(CdEntry {
+            name: name.to_vec(),
+            method,
+            flags,
+            unicode_path: unicode_path(name, extra),
+            encryption: encryption(flags, method, extra),
+            offset: local_header_offset(extra, compressed_size, uncompressed_size, offset),
+        });
         pos = name_end + extra_len + comment_len;
     }
-    Ok(offenders)
+    Ok(entries)
+}
+
+fn extra_field(mut extra: &[u8], wanted: u16) -> Option<&[u8]> {
+    while extra.len() >= 4 {
+        let id = read_u16(extra, 0);
+        let len = read_u16(extra, 2) as usize;
+        let data = extra.get(4..4 + len)?;
+        if id == wanted {
+            return Some(data);
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn unicode_path(name: &[u8], extra: &[u8]) -> Option<String> {
+    let data = extra_field(extra, EXTRA_UNICODE_PATH)?;
+    // Version 1 layout: version (1), CRC-32 of the header name (4), name.
+    if data.len() < 5 || data[0] != 1 || read_u32(data, 1) != crc32(name) {
+        return None;
+    }
+    String::from_utf8(data[5..].to_vec()).ok()
+}
+
+// A saturated offset lives in the Zip64 extra field, after whichever of the
+// uncompressed and compressed sizes are saturated too.
+fn local_header_offset(extra: &[u8], compressed: u32, uncompressed: u32, offset: u32) -> u64 {
+    if offset != 0xFFFFFFFF {
+        return offset as u64;
+    }
+    let skip = [uncompressed, compressed]
+        .iter()
+        .filter(|&&size| size == 0xFFFFFFFF)
+        .count()
+        * 8;
+    extra_field(extra, EXTRA_ZIP64)
+        .and_then(|data| data.get(skip..skip + 8))
+        .map_or(offset as u64, |data| read_u64(data, 0))
+}
+
+fn encryption(flags: u16, method: u16, extra: &[u8]) -> ZipEncryption {
+    if flags & FLAG_ENCRYPTED == 0 {
+        return ZipEncryption::None;
+    }
+    if method == METHOD_AES {
+        // Layout: vendor version (2), vendor id "AE" (2), strength (1),
+        // actual compression method (2).
+        let key_bits = match extra_field(extra, EXTRA_AES).and_then(|data| data.get(4)) {
+            Some(1) => 128,
+            Some(2) => 192,
+            Some(3) => 256,
+            _ => 0,
+        };
+        return ZipEncryption::Aes { key_bits };
+    }
+    if flags & FLAG_STRONG_ENCRYPTION != 0 {
+        return ZipEncryption::Strong;
+    }
+    ZipEncryption::Traditional
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
 }
 
 fn read_u16(buf: &[u8], offset: usize) -> u16 {
@@ -608,7 +608,7 @@ fn iterate_rar_entries() {
     for item in ArchiveIterator::from_read(source).expect("Failed to read archive") {
         match item {
             ArchiveContents::StartOfEntry(name, _) => names.push(name),
-            ArchiveContents::DataChunk(chunk) => {
+            ArchiveContents::DataChunk(chunk, _) => {
                 if names
                     .last()
                     .map(|n| n == "tree/branch2/leaf")
@@ -856,10 +856,13 @@ fn decode_utf8(bytes: &[u8]) -> Result<String> {
     Ok(std::str::from_utf8(bytes)?.to_owned())
 }
 
-fn collect_iterate_results_with_encoding(
+fn collect_iterate_results_with_encoding<D>(
     source: std::fs::File,
-    decode: DecodeCallback,
-) -> Vec<(String, usize)> {
+    decode: D,
+) -> Vec<(String, usize)>
+where
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+{
     let mut results = Vec::new();
     let mut name = String::default();
     let mut size = 0;
@@ -875,7 +878,7 @@ fn collect_iterate_results_with_encoding(
                 assert_eq!(stat.st_size == 0, file_name.ends_with('/'));
                 name = file_name;
             }
-            ArchiveContents::DataChunk(data) => {
+            ArchiveContents::DataChunk(data, _) => {
                 assert!(!name.is_empty());
                 size += data.len();
             }
@@ -964,10 +967,10 @@ fn iterate_tar_stat_fields() {
     }
 }
 
-fn collect_iterate_names_with_encoding(
-    source: std::fs::File,
-    decode: DecodeCallback,
-) -> Vec<String> {
+fn collect_iterate_names_with_encoding<D>(source: std::fs::File, decode: D) -> Vec<String>
+where
+    D: Fn(&[u8]) -> Result<String> + Send + Sync + 'static,
+{
     let mut results = Vec::new();
 
     let mut iter =
@@ -978,7 +981,7 @@ fn collect_iterate_names_with_encoding(
             ArchiveContents::StartOfEntry(file_name, _) => {
                 results.push(file_name);
             }
-            ArchiveContents::DataChunk(_) => {
+            ArchiveContents::DataChunk(..) => {
                 panic!("expected StartOfntry got DataChunk")
             }
             ArchiveContents::EndOfEntry => {
@@ -1115,6 +1118,162 @@ fn uncompress_archive_zip_slip_vulnerability() {
     .unwrap_err();
 }
 
+fn extract_with_link_policy(
+    fixture: &str,
+    dest: &Path,
+    policy: LinkPolicy,
+) -> compress_tools::Result<ExtractReport> {
+    uncompress_archive_with_options(
+        std::fs::File::open(fixture).unwrap(),
+        dest,
+        ExtractOptions::new().link_policy(policy),
+    )
+}
+
+fn assert_invalid_data(result: compress_tools::Result<ExtractReport>) {
+    match result {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_absolute_symlink() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let fixture = "tests/fixtures/symlink-absolute.tar";
+
+    assert_invalid_data(extract_with_link_policy(
+        fixture,
+        dir.path(),
+        LinkPolicy::new().reject_absolute(true),
+    ));
+    assert_invalid_data(extract_with_link_policy(
+        fixture,
+        dir.path(),
+        LinkPolicy::new().reject_escaping(true),
+    ));
+    assert!(std::fs::symlink_metadata(dir.path().join("absolute")).is_err());
+
+    extract_with_link_policy(fixture, dir.path(), LinkPolicy::new()).unwrap();
+    assert_eq!(
+        std::fs::read_link(dir.path().join("absolute")).unwrap(),
+        Path::new("/etc")
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_escaping_symlink() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let fixture = "tests/fixtures/symlink-escape.tar";
+
+    // Only absolute targets are rejected.
+    extract_with_link_policy(fixture, dir.path(), LinkPolicy::new().reject_absolute(true)).unwrap();
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    assert_invalid_data(extract_with_link_policy(
+        fixture,
+        dir.path(),
+        LinkPolicy::strict(),
+    ));
+    // `inner/sibling -> ../inner` stays inside and comes first.
+    assert_eq!(
+        std::fs::read_link(dir.path().join("inner/sibling")).unwrap(),
+        Path::new("../inner")
+    );
+    assert!(std::fs::symlink_metadata(dir.path().join("inner/escape")).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_chained_escaping_symlink() {
+    for fixture in [
+        "tests/fixtures/symlink-chain.tar",
+        "tests/fixtures/symlink-chain-hardlink.tar",
+    ] {
+        // `sub/up -> ..`, then `escape -> sub/up/..` or through a hard link to
+        // `sub/up`: each target stays inside on its own.
+        let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+        extract_with_link_policy(fixture, dir.path(), LinkPolicy::new()).unwrap();
+        assert!(std::fs::symlink_metadata(dir.path().join("escape"))
+            .unwrap()
+            .is_symlink());
+
+        let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+        assert_invalid_data(extract_with_link_policy(
+            fixture,
+            dir.path(),
+            LinkPolicy::new().reject_escaping(true),
+        ));
+        assert_eq!(
+            std::fs::read_link(dir.path().join("sub/up")).unwrap(),
+            Path::new("..")
+        );
+        assert!(std::fs::symlink_metadata(dir.path().join("escape")).is_err());
+
+        let mut tree = MemoryTree::new();
+        let result = uncompress_archive_into_with_options(
+            std::fs::File::open(fixture).unwrap(),
+            &mut tree,
+            ExtractOptions::new().link_policy(LinkPolicy::new().reject_escaping(true)),
+        );
+        match result {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(tree.get("escape").is_none());
+    }
+
+    // A chained target staying inside is allowed.
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    assert_invalid_data(extract_with_link_policy(
+        "tests/fixtures/symlink-chain.tar",
+        dir.path(),
+        LinkPolicy::new().reject_escaping(true),
+    ));
+    assert_eq!(
+        std::fs::read_link(dir.path().join("back")).unwrap(),
+        Path::new("sub/up")
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_write_through_symlink() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let fixture = "tests/fixtures/symlink-traversal.tar";
+
+    extract_with_link_policy(fixture, dir.path(), LinkPolicy::new()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("sub/file")).unwrap(),
+        "written through a symlink\n"
+    );
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    extract_with_link_policy(fixture, dir.path(), LinkPolicy::new().secure_symlinks(true))
+        .unwrap_err();
+    assert!(!dir.path().join("sub/file").exists());
+}
+
+#[test]
+fn uncompress_archive_skip_links() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let report = extract_with_link_policy(
+        "tests/fixtures/links.tar",
+        dir.path(),
+        LinkPolicy::new().skip_links(true),
+    )
+    .unwrap();
+
+    assert_eq!(report.action("links/file.txt"), Some(&EntryAction::Created));
+    assert_eq!(report.action("links/symlink"), Some(&EntryAction::Skipped));
+    assert_eq!(report.action("links/hardlink"), Some(&EntryAction::Skipped));
+    assert!(dir.path().join("links/file.txt").exists());
+    assert!(std::fs::symlink_metadata(dir.path().join("links/symlink")).is_err());
+    assert!(!dir.path().join("links/hardlink").exists());
+}
+
 #[test]
 fn uncompress_archive_absolute_path() {
     let temp_dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
@@ -1314,7 +1473,7 @@ fn iterate_archive_with_password() {
             ArchiveContents::StartOfEntry(name, _stat) => {
                 current_file_name = name;
             }
-            ArchiveContents::DataChunk(dt) => {
+            ArchiveContents::DataChunk(dt, _) => {
                 current_file_content.extend(dt);
             }
             ArchiveContents::EndOfEntry => {
@@ -1449,3 +1608,2380 @@ fn iterator_raw_format_opt_in_accepts_non_archive_bytes() {
     }
     assert_eq!(names, vec!["data".to_string()]);
 }
+
+#[test]
+fn read_option_zip_ignore_crc32() {
+    let mut source = std::fs::File::open("tests/fixtures/bad-crc.zip").unwrap();
+    let mut target = Vec::default();
+
+    uncompress_archive_file(&mut source, &mut target, "file.txt")
+        .expect_err("CRC-32 mismatch must be reported by default");
+
+    target.clear();
+    let written = uncompress_archive_file_with_options(
+        &mut source,
+        &mut target,
+        "file.txt",
+        ReadOptions::new().option(ReadOption::ZipIgnoreCrc32),
+    )
+    .expect("zip:ignorecrc32 should skip the CRC-32 check");
+    assert_eq!(String::from_utf8_lossy(&target), "some_file_content\n");
+    assert_eq!(written, 18);
+}
+
+#[test]
+fn read_option_hdrcharset_decodes_legacy_zip_names() {
+    let source = std::fs::File::open("tests/fixtures/encoding-sjis.zip").unwrap();
+
+    let files = list_archive_files_with_options(
+        source,
+        ReadOptions::new().option(ReadOption::HdrCharset("CP932".to_string())),
+    )
+    .unwrap();
+    assert_eq!(files, vec!["encoding-sjis-japanese-日本語.txt".to_string()]);
+}
+
+#[test]
+fn read_option_raw_is_passed_verbatim() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/bad-crc.zip").unwrap();
+
+    uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new().read_options(
+            ReadOptions::new().option(ReadOption::Raw("zip:ignorecrc32".to_string())),
+        ),
+    )
+    .expect("raw zip:ignorecrc32 should skip the CRC-32 check");
+    assert!(dir.path().join("file.txt").exists());
+}
+
+#[test]
+fn read_option_unknown_is_rejected() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+
+    match list_archive_entries_with_options(
+        source,
+        ReadOptions::new().option(ReadOption::Raw("tar:no-such-option".to_string())),
+    )
+    .expect_err("libarchive must reject unknown options")
+    {
+        Error::Extraction { details, .. } => assert!(details.contains("no-such-option")),
+        other => panic!("expected Error::Extraction, got {other:?}"),
+    }
+}
+
+#[test]
+fn iterator_read_option() {
+    let source = std::fs::File::open("tests/fixtures/encoding-gbk.zip").unwrap();
+
+    let names: Vec<String> = ArchiveIteratorBuilder::new(source)
+        .read_option(ReadOption::HdrCharset("GBK".to_string()))
+        .build()
+        .unwrap()
+        .filter_map(|c| match c {
+            ArchiveContents::StartOfEntry(name, _) => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec!["encoding-gbk-chinese-中文.txt".to_string()]);
+}
+
+#[test]
+fn iterator_read_option_unknown_is_rejected() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+
+    assert!(ArchiveIteratorBuilder::new(source)
+        .read_option(ReadOption::Raw("tar:no-such-option".to_string()))
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn read_option_hdrcharset_tokio() {
+    let source = tokio::fs::File::open("tests/fixtures/encoding-sjis.zip")
+        .await
+        .unwrap();
+
+    let files = tokio_support::list_archive_files_with_options(
+        source,
+        ReadOptions::new().option(ReadOption::HdrCharset("CP932".to_string())),
+    )
+    .await
+    .unwrap();
+    assert_eq!(files, vec!["encoding-sjis-japanese-日本語.txt".to_string()]);
+}
+
+fn decoder_for(
+    encoding: &'static encoding_rs::Encoding,
+) -> impl Fn(&[u8]) -> Result<String> + Send + Sync + 'static {
+    move |bytes: &[u8]| {
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(String::from)
+            .ok_or(Error::Encoding(std::borrow::Cow::Owned(format!(
+                "{} failure",
+                encoding.name()
+            ))))
+    }
+}
+
+#[test]
+fn list_archive_files_with_runtime_encoding() {
+    for (fixture, encoding, expected) in [
+        (
+            "tests/fixtures/encoding-gbk.zip",
+            encoding_rs::GBK,
+            "encoding-gbk-chinese-中文.txt",
+        ),
+        (
+            "tests/fixtures/encoding-sjis.zip",
+            encoding_rs::SHIFT_JIS,
+            "encoding-sjis-japanese-日本語.txt",
+        ),
+    ] {
+        let source = std::fs::File::open(fixture).unwrap();
+        let files = list_archive_files_with_encoding(source, decoder_for(encoding)).unwrap();
+        assert_eq!(files, vec![expected.to_string()]);
+    }
+}
+
+#[test]
+fn iterate_archive_with_stateful_decoder() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+
+    let names: Vec<String> = ArchiveIteratorBuilder::new(source)
+        .decoder(move |bytes| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(String::from_utf8_lossy(bytes).to_uppercase())
+        })
+        .build()
+        .unwrap()
+        .filter_map(|c| match c {
+            ArchiveContents::StartOfEntry(name, _) => Some(name),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+    assert_eq!(names[0], "TREE/");
+}
+
+#[test]
+fn uncompress_archive_with_runtime_encoding() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/encoding-gbk-tree.zip").unwrap();
+
+    uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new()
+            .read_options(ReadOptions::new().decoder(decoder_for(encoding_rs::GBK))),
+    )
+    .expect("Failed to uncompress the file");
+
+    assert!(dir
+        .path()
+        .join("tree/branch1/leaf1-encoding-gbk-chinese-中文.txt")
+        .exists());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn iterate_archive_with_runtime_encoding_tokio() {
+    use futures_util::StreamExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/encoding-sjis.zip")
+        .await
+        .unwrap();
+
+    let names: Vec<String> = tokio_support::ArchiveIteratorBuilder::new(source)
+        .decoder(decoder_for(encoding_rs::SHIFT_JIS))
+        .build()
+        .filter_map(|c| async move {
+            match c {
+                ArchiveContents::StartOfEntry(name, _) => Some(name),
+                _ => None,
+            }
+        })
+        .collect()
+        .await;
+    assert_eq!(names, vec!["encoding-sjis-japanese-日本語.txt".to_string()]);
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn list_archive_files_with_runtime_encoding_futures() {
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/encoding-gbk.zip")
+            .await
+            .unwrap();
+
+        let files = futures_support::list_archive_files_with_encoding(
+            source,
+            decoder_for(encoding_rs::GBK),
+        )
+        .await
+        .unwrap();
+        assert_eq!(files, vec!["encoding-gbk-chinese-中文.txt".to_string()]);
+    });
+}
+
+#[cfg(feature = "charset_detection")]
+fn list_with_detected_charset(path: &str, detector: ZipCharsetDetector) -> Vec<String> {
+    let source = std::fs::File::open(path).unwrap();
+    list_archive_files_with_options(source, ReadOptions::new().detect_zip_charset(detector))
+        .unwrap()
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_legacy_names() {
+    for (path, expected) in [
+        (
+            "tests/fixtures/encoding-gbk.zip",
+            "encoding-gbk-chinese-中文.txt",
+        ),
+        (
+            "tests/fixtures/encoding-sjis.zip",
+            "encoding-sjis-japanese-日本語.txt",
+        ),
+        ("tests/fixtures/encoding-cp437.zip", "ueber-über.txt"),
+        ("tests/fixtures/encoding-cp1252.zip", "café-résumé.txt"),
+    ] {
+        assert_eq!(
+            list_with_detected_charset(path, ZipCharsetDetector::new()),
+            vec![expected.to_string()],
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_trusts_utf8_flag_and_unicode_path() {
+    assert_eq!(
+        list_with_detected_charset(
+            "tests/fixtures/encoding-utf8.zip",
+            ZipCharsetDetector::new()
+        ),
+        vec!["encoding-utf8-chinese-中文.txt".to_string()]
+    );
+    assert_eq!(
+        list_with_detected_charset(
+            "tests/fixtures/encoding-unicode-path.zip",
+            ZipCharsetDetector::new()
+        ),
+        vec!["unicode-path-日本語.txt".to_string()]
+    );
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_mixed_names() {
+    // Only the CP437 name is decoded with the detected charset; the names
+    // libarchive already took from the UTF-8 flag or a Unicode Path extra
+    // field are kept.
+    assert_eq!(
+        list_with_detected_charset(
+            "tests/fixtures/encoding-mixed.zip",
+            ZipCharsetDetector::new()
+        ),
+        vec![
+            "ueber-über.txt".to_string(),
+            "unicode-日本語.txt".to_string(),
+            "utf8-中文.txt".to_string(),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_restricted_candidates() {
+    // Without GBK among the candidates the best remaining guess is used.
+    assert_eq!(
+        list_with_detected_charset(
+            "tests/fixtures/encoding-gbk.zip",
+            ZipCharsetDetector::new().candidates([Charset::Cp1252]),
+        ),
+        vec!["encoding-gbk-chinese-ÖÐÎÄ.txt".to_string()]
+    );
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_non_zip_keeps_decoder() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let options = ReadOptions::new().detect_zip_charset(ZipCharsetDetector::new());
+    let files = list_archive_files_with_options(source, options).unwrap();
+    assert!(files.contains(&"tree/branch1/leaf".to_string()));
+}
+
+#[test]
+#[cfg(feature = "charset_detection")]
+fn detect_zip_charset_uncompress_and_iterate() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let source = std::fs::File::open("tests/fixtures/encoding-gbk-tree.zip").unwrap();
+    let options = ExtractOptions::new()
+        .read_options(ReadOptions::new().detect_zip_charset(ZipCharsetDetector::new()));
+    uncompress_archive_with_options(source, dir.path(), options).unwrap();
+    assert!(dir
+        .path()
+        .join("tree/branch2/leaf2-encoding-gbk-chinese-中文.txt")
+        .exists());
+
+    let source = std::fs::File::open("tests/fixtures/encoding-sjis.zip").unwrap();
+    let names: Vec<String> = ArchiveIteratorBuilder::new(source)
+        .detect_zip_charset(ZipCharsetDetector::new())
+        .build()
+        .unwrap()
+        .filter_map(|content| match content {
+            ArchiveContents::StartOfEntry(name, _) => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec!["encoding-sjis-japanese-日本語.txt".to_string()]);
+}
+
+#[tokio::test]
+#[cfg(all(feature = "tokio_support", feature = "charset_detection"))]
+async fn detect_zip_charset_tokio() {
+    let source = tokio::fs::File::open("tests/fixtures/encoding-gbk.zip")
+        .await
+        .unwrap();
+    let options = ReadOptions::new().detect_zip_charset(ZipCharsetDetector::new());
+    let files = tokio_support::list_archive_files_with_options(source, options)
+        .await
+        .unwrap();
+    assert_eq!(files, vec!["encoding-gbk-chinese-中文.txt".to_string()]);
+}
+
+#[test]
+fn list_archive_files_bytes_non_utf8() {
+    let source = std::fs::File::open("tests/fixtures/non-utf8-names.tar").unwrap();
+    let files = list_archive_files_bytes(source).unwrap();
+    assert_eq!(
+        files,
+        vec![
+            b"dir-\xff/".to_vec(),
+            b"dir-\xff/file-\xe9.txt".to_vec(),
+            b"dir-\xff/link-\xe9.txt".to_vec(),
+        ]
+    );
+
+    let source = std::fs::File::open("tests/fixtures/non-utf8-names.tar").unwrap();
+    assert!(matches!(list_archive_files(source), Err(Error::Utf(_))));
+}
+
+#[test]
+#[cfg(unix)]
+fn list_archive_files_os_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = std::fs::File::open("tests/fixtures/non-utf8-names.tar").unwrap();
+    let files = list_archive_files_os(source).unwrap();
+    assert_eq!(files[1].as_bytes(), b"dir-\xff/file-\xe9.txt");
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_verbatim_names() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let source = std::fs::File::open("tests/fixtures/non-utf8-names.tar").unwrap();
+    uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new().verbatim_names(true),
+    )
+    .unwrap();
+
+    let file = dir
+        .path()
+        .join(OsStr::from_bytes(b"dir-\xff/file-\xe9.txt"));
+    let link = dir
+        .path()
+        .join(OsStr::from_bytes(b"dir-\xff/link-\xe9.txt"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "latin-1 name\n");
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "latin-1 name\n");
+}
+
+#[test]
+fn iterate_archive_pathname_bytes() {
+    let source = std::fs::File::open("tests/fixtures/non-utf8-names.tar").unwrap();
+    let mut iter = ArchiveIteratorBuilder::new(source)
+        .decoder(|bytes: &[u8]| Ok(String::from_utf8_lossy(bytes).into_owned()))
+        .build()
+        .unwrap();
+
+    let mut names = Vec::new();
+    while let Some(content) = iter.next() {
+        if let ArchiveContents::StartOfEntry(name, _) = content {
+            names.push((name, iter.entry_pathname_bytes().unwrap().to_vec()));
+        }
+    }
+    iter.close().unwrap();
+
+    assert_eq!(names[1].0, "dir-\u{FFFD}/file-\u{FFFD}.txt");
+    assert_eq!(names[1].1, b"dir-\xff/file-\xe9.txt");
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn list_archive_files_bytes_tokio() {
+    let source = tokio::fs::File::open("tests/fixtures/non-utf8-names.tar")
+        .await
+        .unwrap();
+    let files = tokio_support::list_archive_files_bytes(source)
+        .await
+        .unwrap();
+    assert_eq!(files[0], b"dir-\xff/");
+}
+
+fn password_read_options(passwords: &[&str]) -> ReadOptions<'static> {
+    passwords
+        .iter()
+        .fold(ReadOptions::new(), |options, password| {
+            options.password(ArchivePassword::new(password).unwrap())
+        })
+}
+
+#[test]
+fn uncompress_archive_file_with_password() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut target = Vec::default();
+    let written = uncompress_archive_file_with_options(
+        source,
+        &mut target,
+        "with-password/file1.txt",
+        password_read_options(&["123"]),
+    )
+    .unwrap();
+    assert_eq!(written, target.len());
+    assert_eq!(String::from_utf8(target).unwrap(), "its encrypted file");
+}
+
+#[test]
+fn uncompress_archive_file_without_password_errors() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut target = Vec::default();
+    assert!(uncompress_archive_file_with_options(
+        source,
+        &mut target,
+        "with-password/file1.txt",
+        ReadOptions::new(),
+    )
+    .is_err());
+}
+
+#[test]
+fn uncompress_archive_with_password_candidates() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let options =
+        ExtractOptions::new().read_options(password_read_options(&["wrong", "also-wrong", "123"]));
+    uncompress_archive_with_options(source, dir.path(), options).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("with-password/file2.txt")).unwrap(),
+        "file 2 in archive encrypted!"
+    );
+}
+
+#[test]
+fn list_archive_entries_with_password() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let entries =
+        list_archive_entries_with_options(source, password_read_options(&["123"])).unwrap();
+    assert!(entries
+        .iter()
+        .any(|entry| entry.path == "with-password/file1.txt"));
+}
+
+#[test]
+fn iterate_archive_with_password_candidates() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut data = Vec::new();
+    for content in ArchiveIteratorBuilder::new(source)
+        .with_password(ArchivePassword::new("wrong").unwrap())
+        .with_password(ArchivePassword::new("123").unwrap())
+        .build()
+        .unwrap()
+    {
+        match content {
+            ArchiveContents::DataChunk(chunk, _) => data.extend(chunk),
+            ArchiveContents::Err(e) => panic!("{:?}", e),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        String::from_utf8(data).unwrap(),
+        "its encrypted filefile 2 in archive encrypted!"
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn uncompress_archive_file_with_password_tokio() {
+    let source = tokio::fs::File::open("tests/fixtures/with-password.zip")
+        .await
+        .unwrap();
+    let mut target = Vec::default();
+    tokio_support::uncompress_archive_file_with_options(
+        source,
+        &mut target,
+        "with-password/file1.txt",
+        password_read_options(&["nope", "123"]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(String::from_utf8(target).unwrap(), "its encrypted file");
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn uncompress_archive_with_password_futures() {
+    smol::block_on(async {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = smol::fs::File::open("tests/fixtures/with-password.zip")
+            .await
+            .unwrap();
+        let options = ExtractOptions::new().read_options(password_read_options(&["123"]));
+        futures_support::uncompress_archive_with_options(source, dir.path(), options)
+            .await
+            .unwrap();
+        assert!(dir.path().join("with-password/file1.txt").exists());
+    });
+}
+
+#[test]
+fn passphrase_callback_borrows_local_state() {
+    let guesses = ["wrong", "also-wrong", "123"];
+    let mut asked = 0;
+    let mut target = Vec::default();
+    {
+        let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+        let options = ReadOptions::new().passphrase_callback(|| {
+            let guess = guesses.get(asked)?;
+            asked += 1;
+            Some(ArchivePassword::new(guess).unwrap())
+        });
+        uncompress_archive_file_with_options(
+            source,
+            &mut target,
+            "with-password/file1.txt",
+            options,
+        )
+        .unwrap();
+    }
+    assert_eq!(asked, 3);
+    assert_eq!(String::from_utf8(target).unwrap(), "its encrypted file");
+}
+
+#[test]
+fn passphrase_callback_giving_up_errors() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut target = Vec::default();
+    let options = ReadOptions::new().passphrase_callback(|| None);
+    assert!(uncompress_archive_file_with_options(
+        source,
+        &mut target,
+        "with-password/file1.txt",
+        options,
+    )
+    .is_err());
+}
+
+#[test]
+fn iterate_archive_with_passphrase_callback() {
+    let asked = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = asked.clone();
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut data = Vec::new();
+    for content in ArchiveIteratorBuilder::new(source)
+        .with_password(ArchivePassword::new("wrong").unwrap())
+        .passphrase_callback(move || {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Some(ArchivePassword::new("123").unwrap())
+        })
+        .build()
+        .unwrap()
+    {
+        match content {
+            ArchiveContents::DataChunk(chunk, _) => data.extend(chunk),
+            ArchiveContents::Err(e) => panic!("{:?}", e),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        String::from_utf8(data).unwrap(),
+        "its encrypted filefile 2 in archive encrypted!"
+    );
+    // The accepted password is remembered for the second entry.
+    assert_eq!(asked.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn iterate_archive_with_passphrase_callback_tokio() {
+    use futures_util::stream::StreamExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/with-password.zip")
+        .await
+        .unwrap();
+    let mut stream = tokio_support::ArchiveIteratorBuilder::new(source)
+        .passphrase_callback(|| Some(ArchivePassword::new("123").unwrap()))
+        .build();
+    let mut data = Vec::new();
+    while let Some(content) = stream.next().await {
+        match content {
+            ArchiveContents::DataChunk(chunk, _) => data.extend(chunk),
+            ArchiveContents::Err(e) => panic!("{:?}", e),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        String::from_utf8(data).unwrap(),
+        "its encrypted filefile 2 in archive encrypted!"
+    );
+}
+
+#[test]
+fn archive_encryption_status() {
+    for (path, expected) in [
+        (
+            "tests/fixtures/with-password.zip",
+            ArchiveEncryption::Encrypted,
+        ),
+        (
+            "tests/fixtures/mixed-encryption.zip",
+            ArchiveEncryption::Encrypted,
+        ),
+        ("tests/fixtures/test.zip", ArchiveEncryption::NotEncrypted),
+        ("tests/fixtures/tree.tar", ArchiveEncryption::Unsupported),
+    ] {
+        let source = std::fs::File::open(path).unwrap();
+        assert_eq!(archive_encryption(source).unwrap(), expected, "{}", path);
+    }
+}
+
+#[test]
+fn list_archive_entries_reports_encryption() {
+    let source = std::fs::File::open("tests/fixtures/mixed-encryption.zip").unwrap();
+    let entries: Vec<(String, bool)> = list_archive_entries(source)
+        .unwrap()
+        .into_iter()
+        .map(|entry| (entry.path, entry.is_encrypted))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("plain.txt".to_string(), false),
+            ("secret.txt".to_string(), true),
+        ]
+    );
+}
+
+#[test]
+fn list_zip_encryption_schemes() {
+    let source = std::fs::File::open("tests/fixtures/mixed-encryption.zip").unwrap();
+    assert_eq!(
+        list_zip_encryption(source).unwrap(),
+        vec![
+            ZipEntryEncryption {
+                path: "plain.txt".to_string(),
+                encryption: ZipEncryption::None,
+            },
+            ZipEntryEncryption {
+                path: "secret.txt".to_string(),
+                encryption: ZipEncryption::Traditional,
+            },
+        ]
+    );
+
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let schemes: Vec<ZipEncryption> = list_zip_encryption(source)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.encryption)
+        .collect();
+    assert_eq!(
+        schemes,
+        vec![
+            ZipEncryption::None,
+            ZipEncryption::Aes { key_bits: 256 },
+            ZipEncryption::Aes { key_bits: 256 },
+        ]
+    );
+
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    assert!(list_zip_encryption(source).unwrap().is_empty());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn archive_encryption_tokio() {
+    let source = tokio::fs::File::open("tests/fixtures/with-password.zip")
+        .await
+        .unwrap();
+    assert_eq!(
+        tokio_support::archive_encryption(source).await.unwrap(),
+        ArchiveEncryption::Encrypted
+    );
+}
+
+fn write_encrypted_zip(method: EncryptionMethod) -> Vec<u8> {
+    let mut writer = ArchiveWriterBuilder::new(Vec::new(), ArchiveFormat::Zip)
+        .with_password(ArchivePassword::new("secret").unwrap())
+        .encryption(method)
+        .build()
+        .unwrap();
+    writer.append_directory("docs/").unwrap();
+    let content = b"classified content\n";
+    writer
+        .append_file("docs/plan.txt", &content[..], content.len() as u64)
+        .unwrap();
+    writer.finish().unwrap()
+}
+
+#[test]
+fn write_encrypted_zip_round_trip() {
+    for (method, encryption) in [
+        (
+            EncryptionMethod::Aes256,
+            ZipEncryption::Aes { key_bits: 256 },
+        ),
+        (
+            EncryptionMethod::Aes128,
+            ZipEncryption::Aes { key_bits: 128 },
+        ),
+        (EncryptionMethod::Traditional, ZipEncryption::Traditional),
+    ] {
+        let archive = write_encrypted_zip(method);
+
+        let schemes: Vec<ZipEncryption> = list_zip_encryption(Cursor::new(&archive))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.encryption)
+            .collect();
+        assert_eq!(schemes, vec![ZipEncryption::None, encryption]);
+
+        let mut target = Vec::new();
+        uncompress_archive_file_with_options(
+            Cursor::new(&archive),
+            &mut target,
+            "docs/plan.txt",
+            password_read_options(&["secret"]),
+        )
+        .unwrap();
+        assert_eq!(target, b"classified content\n", "{:?}", method);
+
+        let mut target = Vec::new();
+        assert!(uncompress_archive_file_with_options(
+            Cursor::new(&archive),
+            &mut target,
+            "docs/plan.txt",
+            password_read_options(&["wrong"]),
+        )
+        .is_err());
+        assert!(
+            uncompress_archive_file(Cursor::new(&archive), &mut target, "docs/plan.txt").is_err()
+        );
+    }
+}
+
+#[test]
+fn write_encrypted_zip_read_by_iterator() {
+    let archive = write_encrypted_zip(EncryptionMethod::Aes256);
+    let mut content = Vec::new();
+    for entry in ArchiveIteratorBuilder::new(Cursor::new(archive))
+        .with_password(ArchivePassword::new("secret").unwrap())
+        .build()
+        .unwrap()
+    {
+        match entry {
+            ArchiveContents::DataChunk(chunk, _) => content.extend(chunk),
+            ArchiveContents::Err(e) => panic!("{}", e),
+            _ => {}
+        }
+    }
+    assert_eq!(content, b"classified content\n");
+}
+
+#[test]
+fn write_password_defaults_to_aes256() {
+    let mut writer = ArchiveWriterBuilder::new(Vec::new(), ArchiveFormat::Zip)
+        .with_password(ArchivePassword::new("secret").unwrap())
+        .build()
+        .unwrap();
+    writer.append_file("a.txt", &b"a"[..], 1).unwrap();
+    let archive = writer.finish().unwrap();
+
+    let entries = list_zip_encryption(Cursor::new(archive)).unwrap();
+    assert_eq!(entries[0].encryption, ZipEncryption::Aes { key_bits: 256 });
+}
+
+#[test]
+fn write_rejects_invalid_encryption_settings() {
+    let error = ArchiveWriterBuilder::new(Vec::new(), ArchiveFormat::Zip)
+        .encryption(EncryptionMethod::Aes128)
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(error, Error::Io(e) if e.kind() == ErrorKind::InvalidInput));
+
+    let error = ArchiveWriterBuilder::new(Vec::new(), ArchiveFormat::Tar)
+        .with_password(ArchivePassword::new("secret").unwrap())
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(error, Error::Io(e) if e.kind() == ErrorKind::InvalidInput));
+}
+
+#[test]
+fn write_tar_round_trip() {
+    let mut writer = ArchiveWriterBuilder::new(Vec::new(), ArchiveFormat::Tar)
+        .build()
+        .unwrap();
+    writer.append_directory("tree/").unwrap();
+    writer.append_file("tree/leaf", &b"leaf\n"[..], 5).unwrap();
+    assert!(writer.append_file("tree/short", &b"abc"[..], 5).is_err());
+    let archive = writer.finish().unwrap();
+
+    let mut target = Vec::new();
+    uncompress_archive_file(Cursor::new(&archive), &mut target, "tree/leaf").unwrap();
+    assert_eq!(target, b"leaf\n");
+}
+
+#[test]
+fn archive_index_zip_random_access() {
+    let source = std::fs::File::open("tests/fixtures/test.zip").unwrap();
+    let mut index = ArchiveIndex::new(source).unwrap();
+    assert!(index.is_random_access());
+
+    for path in ["content/nested/second", "content/first", "content/third"] {
+        let mut expected = Vec::new();
+        let source = std::fs::File::open("tests/fixtures/test.zip").unwrap();
+        uncompress_archive_file(source, &mut expected, path).unwrap();
+
+        let mut content = Vec::new();
+        index.open(path).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content, expected, "{}", path);
+    }
+
+    let mut content = Vec::new();
+    index
+        .open("content/nested/")
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert!(content.is_empty());
+
+    let error = index.open("content/missing").err().unwrap();
+    assert!(matches!(error, Error::Io(e) if e.kind() == ErrorKind::NotFound));
+}
+
+#[test]
+fn archive_index_tar_sequential() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let mut index = ArchiveIndex::new(source).unwrap();
+    assert!(!index.is_random_access());
+
+    for _ in 0..2 {
+        let mut content = String::new();
+        index
+            .open("tree/branch2/leaf")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "Goodbye World\n");
+    }
+
+    let error = index.open("tree/missing").err().unwrap();
+    assert!(matches!(error, Error::Io(e) if e.kind() == ErrorKind::NotFound));
+}
+
+#[test]
+fn archive_index_7z_sequential() {
+    let source = std::fs::File::open("tests/fixtures/tree.7z").unwrap();
+    let mut index = ArchiveIndex::new(source).unwrap();
+    assert!(!index.is_random_access());
+
+    for path in ["tree/branch1/leaf", "tree/branch2/leaf"] {
+        let mut content = Vec::new();
+        index.open(path).unwrap().read_to_end(&mut content).unwrap();
+        let mut expected = Vec::new();
+        uncompress_archive_file(
+            std::fs::File::open("tests/fixtures/tree.7z").unwrap(),
+            &mut expected,
+            path,
+        )
+        .unwrap();
+        assert_eq!(content, expected);
+    }
+}
+
+#[test]
+fn archive_index_encrypted_zip() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut index = ArchiveIndex::with_options(source, password_read_options(&["123"])).unwrap();
+
+    let mut content = String::new();
+    index
+        .open("with-password/file2.txt")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "file 2 in archive encrypted!");
+}
+
+#[test]
+fn archive_index_unicode_path_and_deflate64() {
+    let source = std::fs::File::open("tests/fixtures/encoding-unicode-path.zip").unwrap();
+    let mut index = ArchiveIndex::new(source).unwrap();
+    let mut content = Vec::new();
+    index
+        .open("unicode-path-日本語.txt")
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert_eq!(content.len(), 13);
+
+    let source = std::fs::File::open("tests/fixtures/deflate64.zip").unwrap();
+    let mut index = ArchiveIndex::new(source).unwrap();
+    assert!(matches!(
+        index.open("file.txt").err().unwrap(),
+        Error::UnsupportedZipCompression(_)
+    ));
+}
+
+#[test]
+fn uncompress_many_files_in_one_pass() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let mut branch1 = Vec::new();
+    let mut branch2 = Vec::new();
+    let mut branch2_copy = Vec::new();
+    let mut missing_target = Vec::new();
+
+    let missing = uncompress_archive_files(
+        source,
+        [
+            ("tree/branch2/leaf", &mut branch2),
+            ("tree/missing", &mut missing_target),
+            ("tree/branch1/leaf", &mut branch1),
+            ("tree/branch2/leaf", &mut branch2_copy),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(missing, vec!["tree/missing".to_string()]);
+    assert_eq!(String::from_utf8(branch1).unwrap(), "Hello World\n");
+    assert_eq!(String::from_utf8(branch2).unwrap(), "Goodbye World\n");
+    assert_eq!(String::from_utf8(branch2_copy).unwrap(), "Goodbye World\n");
+    assert!(missing_target.is_empty());
+}
+
+#[test]
+fn uncompress_many_files_with_password() {
+    let source = std::fs::File::open("tests/fixtures/with-password.zip").unwrap();
+    let mut file1 = Vec::new();
+    let mut file2 = Vec::new();
+
+    let missing = uncompress_archive_files_with_options(
+        source,
+        [
+            ("with-password/file1.txt", &mut file1),
+            ("with-password/file2.txt", &mut file2),
+        ],
+        password_read_options(&["123"]),
+    )
+    .unwrap();
+
+    assert!(missing.is_empty());
+    assert_eq!(String::from_utf8(file1).unwrap(), "its encrypted file");
+    assert_eq!(
+        String::from_utf8(file2).unwrap(),
+        "file 2 in archive encrypted!"
+    );
+}
+
+#[test]
+fn entry_matcher_globs() {
+    let matcher = EntryMatcher::new().include("tree/*/leaf");
+    assert!(matcher.matches("tree/branch1/leaf"));
+    assert!(!matcher.matches("other/tree/branch1/leaf"));
+    assert!(!matcher.matches("tree/branch1"));
+
+    // A matching directory selects everything below it.
+    let matcher = EntryMatcher::new().include("tree/branch[12]");
+    assert!(matcher.matches("tree/branch2/"));
+    assert!(matcher.matches("tree/branch2/leaf"));
+    assert!(!matcher.matches("tree/branch3/leaf"));
+
+    let matcher = EntryMatcher::new().include_unanchored("le?f");
+    assert!(matcher.matches("tree/branch1/leaf"));
+    assert!(!matcher.matches("tree/branch1/leaves"));
+
+    // Excludes are unanchored unless asked otherwise.
+    let matcher = EntryMatcher::new().exclude("*.o").exclude("build");
+    assert!(matcher.matches("src/main.c"));
+    assert!(!matcher.matches("src/main.o"));
+    assert!(!matcher.matches("src/build/out.c"));
+    let matcher = EntryMatcher::new().exclude_anchored("build");
+    assert!(matcher.matches("src/build/out.c"));
+    assert!(!matcher.matches("build/out.c"));
+
+    let matcher = EntryMatcher::new().include("[!a-c]*").include("\\*literal");
+    assert!(matcher.matches("delta"));
+    assert!(!matcher.matches("bravo"));
+    assert!(matcher.matches("*literal"));
+    assert!(!matcher.matches("bliteral"));
+}
+
+#[test]
+fn entry_matcher_list_and_extract() {
+    let options = || {
+        ReadOptions::new().matcher(
+            EntryMatcher::new()
+                .include("tree/branch*")
+                .exclude("branch1"),
+        )
+    };
+
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    assert_eq!(
+        list_archive_files_with_options(source, options()).unwrap(),
+        vec!["tree/branch2/", "tree/branch2/leaf"]
+    );
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new().read_options(options()),
+    )
+    .unwrap();
+    assert!(dir.path().join("tree/branch2/leaf").exists());
+    assert!(!dir.path().join("tree/branch1").exists());
+}
+
+#[test]
+fn entry_matcher_iterator() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let names: Vec<String> = ArchiveIteratorBuilder::new(source)
+        .matcher(EntryMatcher::new().include_unanchored("leaf"))
+        .build()
+        .unwrap()
+        .filter_map(|content| match content {
+            ArchiveContents::StartOfEntry(name, _) => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, vec!["tree/branch1/leaf", "tree/branch2/leaf"]);
+}
+
+fn list_tree_with_matcher(matcher: EntryMatcher) -> Vec<String> {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    list_archive_files_with_options(source, ReadOptions::new().matcher(matcher)).unwrap()
+}
+
+#[test]
+fn entry_matcher_time_and_size() {
+    let at = |secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+
+    // tree/branch1/leaf is 12 bytes modified at 1556038389, tree/branch2/leaf
+    // 14 bytes at 1556038397.
+    assert_eq!(
+        list_tree_with_matcher(EntryMatcher::new().modified_after(at(1556038389))),
+        vec!["tree/branch2/leaf"]
+    );
+    assert_eq!(
+        list_tree_with_matcher(
+            EntryMatcher::new()
+                .modified_after(at(1556038340))
+                .modified_before(at(1556038389))
+        ),
+        vec!["tree/branch1/", "tree/branch2/"]
+    );
+    assert_eq!(
+        list_tree_with_matcher(EntryMatcher::new().min_size(1).max_size(12)),
+        vec!["tree/branch1/leaf"]
+    );
+    assert_eq!(
+        list_tree_with_matcher(EntryMatcher::new().include("tree/branch2").min_size(13)),
+        vec!["tree/branch2/leaf"]
+    );
+}
+
+#[test]
+fn entry_matcher_ownership() {
+    assert_eq!(
+        list_tree_with_matcher(EntryMatcher::new().uid(0).uid(1000)).len(),
+        5
+    );
+    assert!(list_tree_with_matcher(EntryMatcher::new().uid(0)).is_empty());
+    assert_eq!(
+        list_tree_with_matcher(EntryMatcher::new().gid(1000).gname("jonathas")).len(),
+        5
+    );
+    assert!(list_tree_with_matcher(EntryMatcher::new().uname("root")).is_empty());
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let matcher = EntryMatcher::new().uname("jonathas").max_size(12);
+    uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new().read_options(ReadOptions::new().matcher(matcher)),
+    )
+    .unwrap();
+    assert!(dir.path().join("tree/branch1/leaf").exists());
+    assert!(!dir.path().join("tree/branch2/leaf").exists());
+}
+
+#[derive(Default)]
+struct RecordingSink {
+    events: Vec<String>,
+    files: std::collections::HashMap<std::path::PathBuf, Vec<u8>>,
+    metadata: Vec<EntryMetadata>,
+}
+
+impl ExtractSink for RecordingSink {
+    type File<'a> = &'a mut Vec<u8>;
+
+    fn create_dir(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.events
+            .push(format!("dir {} {:o}", path.display(), metadata.mode));
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<&mut Vec<u8>> {
+        self.events.push(format!(
+            "file {} {:o} {}",
+            path.display(),
+            metadata.mode,
+            metadata.size
+        ));
+        Ok(self.files.entry(path.to_path_buf()).or_default())
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path, _: &EntryMetadata) -> Result<()> {
+        self.events.push(format!(
+            "symlink {} -> {}",
+            path.display(),
+            target.display()
+        ));
+        Ok(())
+    }
+
+    fn hardlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        self.events.push(format!(
+            "hardlink {} -> {}",
+            path.display(),
+            target.display()
+        ));
+        Ok(())
+    }
+
+    fn set_metadata(&mut self, path: &Path, metadata: &EntryMetadata) -> Result<()> {
+        self.events.push(format!("metadata {}", path.display()));
+        self.metadata.push(metadata.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn uncompress_archive_into_custom_sink() {
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    let mut sink = RecordingSink::default();
+    uncompress_archive_into(source, &mut sink).unwrap();
+
+    assert_eq!(
+        sink.events,
+        vec![
+            "dir links 755",
+            "file links/file.txt 640 15",
+            "metadata links/file.txt",
+            "symlink links/symlink -> file.txt",
+            "hardlink links/hardlink -> links/file.txt",
+            "metadata links",
+        ]
+    );
+    assert_eq!(sink.files[Path::new("links/file.txt")], b"linked content\n");
+
+    let metadata = &sink.metadata[0];
+    assert_eq!(
+        metadata.mtime,
+        Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1600000000))
+    );
+    assert_eq!((metadata.uid, metadata.gid), (1000, 1000));
+    assert_eq!(metadata.uname.as_deref(), Some("user"));
+    assert_eq!(metadata.gname.as_deref(), Some("group"));
+}
+
+#[test]
+fn uncompress_archive_into_custom_sink_with_extract_options() {
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    let mut sink = RecordingSink::default();
+    let options = ExtractOptions::new()
+        .link_policy(LinkPolicy::new().skip_links(true))
+        .umask(0o077);
+    uncompress_archive_into_with_options(source, &mut sink, options).unwrap();
+    assert_eq!(
+        sink.events,
+        vec![
+            "dir links 700",
+            "file links/file.txt 600 15",
+            "metadata links/file.txt",
+            "metadata links",
+        ]
+    );
+
+    let source = std::fs::File::open("tests/fixtures/symlink-absolute.tar").unwrap();
+    let options = ExtractOptions::new().link_policy(LinkPolicy::strict());
+    let error =
+        uncompress_archive_into_with_options(source, &mut RecordingSink::default(), options)
+            .unwrap_err();
+    assert!(matches!(error, Error::Io(ref e) if e.kind() == ErrorKind::InvalidData));
+
+    let source = std::fs::File::open("tests/fixtures/special.tar").unwrap();
+    let options = ExtractOptions::new().special_files(SpecialFilePolicy::Error);
+    let error =
+        uncompress_archive_into_with_options(source, &mut RecordingSink::default(), options)
+            .unwrap_err();
+    assert!(matches!(error, Error::Io(ref e) if e.kind() == ErrorKind::InvalidData));
+}
+
+#[test]
+fn uncompress_archive_into_custom_sink_with_matcher() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let mut sink = RecordingSink::default();
+    let options = ExtractOptions::new()
+        .read_options(ReadOptions::new().matcher(EntryMatcher::new().include("tree/branch2/leaf")));
+    uncompress_archive_into_with_options(source, &mut sink, options).unwrap();
+
+    assert_eq!(
+        sink.files.keys().collect::<Vec<_>>(),
+        vec![Path::new("tree/branch2/leaf")]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_into_disk_sink() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    uncompress_archive_into(source, &mut DiskSink::new(dir.path())).unwrap();
+
+    let file = dir.path().join("links/file.txt");
+    assert_eq!(std::fs::read(&file).unwrap(), b"linked content\n");
+    let metadata = std::fs::metadata(&file).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(metadata.mtime(), 1600000000);
+    assert_eq!(
+        std::fs::read_link(dir.path().join("links/symlink")).unwrap(),
+        Path::new("file.txt")
+    );
+    assert_eq!(
+        std::fs::metadata(dir.path().join("links/hardlink"))
+            .unwrap()
+            .ino(),
+        metadata.ino()
+    );
+}
+
+#[test]
+fn uncompress_archive_into_disk_sink_does_not_follow_symlinks() {
+    // `evil -> ../outside` followed by `evil/passwd`.
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let dest = dir.path().join("dest");
+    std::fs::create_dir(dir.path().join("outside")).unwrap();
+    let source = std::fs::File::open("tests/fixtures/symlink-child.tar").unwrap();
+
+    uncompress_archive_into(source, &mut DiskSink::new(&dest)).unwrap_err();
+    assert!(!dir.path().join("outside/passwd").exists());
+}
+
+#[test]
+fn uncompress_archive_to_memory_tree() {
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    let tree = uncompress_archive_to_memory(source).unwrap();
+
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.file("links/file.txt"), Some(&b"linked content\n"[..]));
+    assert_eq!(tree.file("links/hardlink"), Some(&b"linked content\n"[..]));
+    assert_eq!(tree.file("links/symlink"), None);
+    match tree.get("links/symlink") {
+        Some(MemoryNode::Symlink { target, .. }) => assert_eq!(target, Path::new("file.txt")),
+        node => panic!("unexpected node {:?}", node),
+    }
+
+    let dir = tree.get("links").unwrap();
+    assert!(matches!(dir, MemoryNode::Directory { .. }));
+    assert_eq!(dir.metadata().mode, 0o755);
+    assert_eq!(dir.metadata().uname.as_deref(), Some("user"));
+
+    let paths: Vec<_> = tree.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(
+        paths,
+        ["links", "links/file.txt", "links/hardlink", "links/symlink"]
+            .iter()
+            .map(std::path::PathBuf::from)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn uncompress_archive_to_memory_with_matcher() {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let options = ReadOptions::new().matcher(EntryMatcher::new().include("tree/branch2"));
+    let tree = uncompress_archive_to_memory_with_options(source, options).unwrap();
+
+    assert_eq!(
+        tree.file("tree/branch2/leaf"),
+        Some(&b"Goodbye World\n"[..])
+    );
+    assert!(tree.get("tree/branch1/leaf").is_none());
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_at_directory_handle() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let handle = std::fs::File::open(dir.path()).unwrap();
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    uncompress_archive_at(source, &handle, Ownership::Ignore).unwrap();
+
+    let file = dir.path().join("links/file.txt");
+    assert_eq!(std::fs::read(&file).unwrap(), b"linked content\n");
+    let metadata = std::fs::metadata(&file).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+    assert_eq!(metadata.mtime(), 1600000000);
+    let links = std::fs::metadata(dir.path().join("links")).unwrap();
+    assert_eq!(links.permissions().mode() & 0o7777, 0o755);
+    assert_eq!(
+        std::fs::read_link(dir.path().join("links/symlink")).unwrap(),
+        Path::new("file.txt")
+    );
+    assert_eq!(
+        std::fs::metadata(dir.path().join("links/hardlink"))
+            .unwrap()
+            .ino(),
+        metadata.ino()
+    );
+
+    // Extracting again replaces the existing entries.
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    uncompress_archive_at(source, &handle, Ownership::Ignore).unwrap();
+    assert_eq!(std::fs::read(&file).unwrap(), b"linked content\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_at_does_not_follow_symlinks() {
+    use std::os::fd::AsFd;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let outside = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("tree")).unwrap();
+    let handle = std::fs::File::open(dir.path()).unwrap();
+
+    // Without the directory entry, the symlink is found on the way to the file.
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    let options = ExtractOptions::new()
+        .read_options(ReadOptions::new().matcher(EntryMatcher::new().include("tree/branch1/leaf")));
+    let mut sink = DirFdSink::new(handle.as_fd());
+    let error = uncompress_archive_into_with_options(source, &mut sink, options).unwrap_err();
+    assert!(
+        matches!(error, Error::Io(_)),
+        "unexpected error {:?}",
+        error
+    );
+    assert_eq!(std::fs::read_dir(outside.path()).unwrap().count(), 0);
+
+    // The directory entry replaces the symlink.
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    uncompress_archive_at(source, &handle, Ownership::Ignore).unwrap();
+    assert!(std::fs::symlink_metadata(dir.path().join("tree"))
+        .unwrap()
+        .is_dir());
+    assert_eq!(
+        std::fs::read(dir.path().join("tree/branch1/leaf")).unwrap(),
+        b"Hello World\n"
+    );
+    assert_eq!(std::fs::read_dir(outside.path()).unwrap().count(), 0);
+}
+
+fn extract_tree_with_conflict_policy(
+    dest: &Path,
+    policy: ConflictPolicy<'static>,
+) -> Result<ExtractReport> {
+    let source = std::fs::File::open("tests/fixtures/tree.tar").unwrap();
+    uncompress_archive_with_options(source, dest, ExtractOptions::new().conflict_policy(policy))
+}
+
+#[test]
+fn conflict_policy_report() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let report = extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+    assert!(report
+        .entries
+        .iter()
+        .all(|entry| entry.action == EntryAction::Created));
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Created)
+    );
+
+    let leaf = dir.path().join("tree/branch1/leaf");
+    std::fs::write(&leaf, "edited").unwrap();
+    let report = extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+    assert_eq!(report.action("tree"), Some(&EntryAction::Merged));
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Overwritten)
+    );
+    assert_eq!(std::fs::read_to_string(&leaf).unwrap(), "Hello World\n");
+}
+
+#[test]
+fn conflict_policy_skip_and_error() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+    let leaf = dir.path().join("tree/branch1/leaf");
+    std::fs::write(&leaf, "edited").unwrap();
+
+    let report = extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Skip).unwrap();
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Skipped)
+    );
+    assert_eq!(
+        report.action("tree/branch2/leaf"),
+        Some(&EntryAction::Skipped)
+    );
+    assert_eq!(std::fs::read_to_string(&leaf).unwrap(), "edited");
+
+    match extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Error) {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::AlreadyExists),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(std::fs::read_to_string(&leaf).unwrap(), "edited");
+}
+
+#[test]
+fn conflict_policy_ignores_entries_from_the_same_run() {
+    for policy in [ConflictPolicy::Skip, ConflictPolicy::Error] {
+        let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+        let source = std::fs::File::open("tests/fixtures/duplicate.tar").unwrap();
+        let report = uncompress_archive_with_options(
+            source,
+            dir.path(),
+            ExtractOptions::new().conflict_policy(policy),
+        )
+        .unwrap();
+        let actions: Vec<_> = report.entries.iter().map(|entry| &entry.action).collect();
+        assert_eq!(actions, [&EntryAction::Created, &EntryAction::Overwritten]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("duplicate.txt")).unwrap(),
+            "second\n"
+        );
+    }
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    std::fs::write(dir.path().join("duplicate.txt"), "edited").unwrap();
+    let source = std::fs::File::open("tests/fixtures/duplicate.tar").unwrap();
+    let report = uncompress_archive_with_options(
+        source,
+        dir.path(),
+        ExtractOptions::new().conflict_policy(ConflictPolicy::Skip),
+    )
+    .unwrap();
+    assert_eq!(report.action("duplicate.txt"), Some(&EntryAction::Skipped));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("duplicate.txt")).unwrap(),
+        "edited"
+    );
+}
+
+#[test]
+fn conflict_policy_rejects_suffix_with_separator() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+
+    let policies = [
+        ConflictPolicy::RenameWithSuffix("/../../escaped".to_string()),
+        ConflictPolicy::Callback(Box::new(|_, _| {
+            Ok(ConflictResolution::RenameWithSuffix(
+                "/../escaped".to_string(),
+            ))
+        })),
+    ];
+    for policy in policies {
+        match extract_tree_with_conflict_policy(dir.path(), policy) {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+    assert!(std::fs::symlink_metadata(dir.path().join("escaped")).is_err());
+    assert!(std::fs::symlink_metadata(dir.path().join("tree/escaped")).is_err());
+}
+
+#[test]
+fn conflict_policy_keep_newer() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+    let newer = dir.path().join("tree/branch1/leaf");
+    std::fs::write(&newer, "edited").unwrap();
+    let older = dir.path().join("tree/branch2/leaf");
+    std::fs::write(&older, "edited").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&older)
+        .unwrap()
+        .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000))
+        .unwrap();
+
+    let report = extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::KeepNewer).unwrap();
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Skipped)
+    );
+    assert_eq!(
+        report.action("tree/branch2/leaf"),
+        Some(&EntryAction::Overwritten)
+    );
+    assert_eq!(std::fs::read_to_string(&newer).unwrap(), "edited");
+    assert_eq!(std::fs::read_to_string(&older).unwrap(), "Goodbye World\n");
+}
+
+#[test]
+fn conflict_policy_rename_and_callback() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    extract_tree_with_conflict_policy(dir.path(), ConflictPolicy::Overwrite).unwrap();
+    let leaf = dir.path().join("tree/branch1/leaf");
+    std::fs::write(&leaf, "edited").unwrap();
+
+    let report = extract_tree_with_conflict_policy(
+        dir.path(),
+        ConflictPolicy::RenameWithSuffix(".new".to_string()),
+    )
+    .unwrap();
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Renamed("tree/branch1/leaf.new".into()))
+    );
+    assert_eq!(std::fs::read_to_string(&leaf).unwrap(), "edited");
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("tree/branch1/leaf.new")).unwrap(),
+        "Hello World\n"
+    );
+
+    let policy = ConflictPolicy::Callback(Box::new(|path, metadata| {
+        assert_eq!(metadata.uid, 1000);
+        if path.ends_with("branch1/leaf") {
+            Ok(ConflictResolution::Skip)
+        } else {
+            Ok(ConflictResolution::Overwrite)
+        }
+    }));
+    let report = extract_tree_with_conflict_policy(dir.path(), policy).unwrap();
+    assert_eq!(
+        report.action("tree/branch1/leaf"),
+        Some(&EntryAction::Skipped)
+    );
+    assert_eq!(
+        report.action("tree/branch2/leaf"),
+        Some(&EntryAction::Overwritten)
+    );
+    assert_eq!(std::fs::read_to_string(&leaf).unwrap(), "edited");
+}
+
+#[test]
+#[cfg(unix)]
+fn conflict_policy_rename_follows_hard_links() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let options = || {
+        ExtractOptions::new().conflict_policy(ConflictPolicy::RenameWithSuffix(".new".to_string()))
+    };
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    uncompress_archive_with_options(source, dir.path(), options()).unwrap();
+    let source = std::fs::File::open("tests/fixtures/links.tar").unwrap();
+    let report = uncompress_archive_with_options(source, dir.path(), options()).unwrap();
+
+    assert_eq!(
+        report.action("links/hardlink"),
+        Some(&EntryAction::Renamed("links/hardlink.new".into()))
+    );
+    assert_eq!(
+        std::fs::metadata(dir.path().join("links/hardlink.new"))
+            .unwrap()
+            .ino(),
+        std::fs::metadata(dir.path().join("links/file.txt.new"))
+            .unwrap()
+            .ino()
+    );
+    assert_ne!(
+        std::fs::metadata(dir.path().join("links/hardlink.new"))
+            .unwrap()
+            .ino(),
+        std::fs::metadata(dir.path().join("links/file.txt"))
+            .unwrap()
+            .ino()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_skip_special_files_and_strip_bits() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let options = ExtractOptions::new()
+        .special_files(SpecialFilePolicy::Skip)
+        .strip_special_bits(true);
+    let report = uncompress_archive_with_options(
+        std::fs::File::open("tests/fixtures/special.tar").unwrap(),
+        dir.path(),
+        options,
+    )
+    .unwrap();
+
+    assert_eq!(report.action("special/null"), Some(&EntryAction::Skipped));
+    assert_eq!(report.action("special/fifo"), Some(&EntryAction::Skipped));
+    assert!(std::fs::symlink_metadata(dir.path().join("special/null")).is_err());
+    assert!(std::fs::symlink_metadata(dir.path().join("special/fifo")).is_err());
+
+    let stripped = |path: &str| {
+        report
+            .entries
+            .iter()
+            .find(|entry| entry.path == Path::new(path))
+            .unwrap()
+            .stripped_mode
+    };
+    assert_eq!(stripped("special/setuid"), 0o4000);
+    assert_eq!(stripped("special/sticky"), 0o1000);
+    assert_eq!(stripped("special"), 0);
+
+    let mode = |path: &str| {
+        std::fs::metadata(dir.path().join(path))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o7777
+    };
+    assert_eq!(mode("special/setuid"), 0o755);
+    assert_eq!(mode("special/sticky"), 0o777);
+}
+
+#[test]
+fn uncompress_archive_reject_special_files() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let options = ExtractOptions::new().special_files(SpecialFilePolicy::Error);
+    let result = uncompress_archive_with_options(
+        std::fs::File::open("tests/fixtures/special.tar").unwrap(),
+        dir.path(),
+        options,
+    );
+
+    match result {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(dir.path().join("special/setuid").exists());
+    assert!(std::fs::symlink_metadata(dir.path().join("special/null")).is_err());
+}
+
+#[cfg(unix)]
+fn extract_owner(fixture: &str, path: &str, options: ExtractOptions<'_>) -> (u32, u32) {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open(fixture).unwrap();
+    uncompress_archive_with_options(source, dir.path(), options.ownership(Ownership::Preserve))
+        .unwrap();
+    let metadata = std::fs::symlink_metadata(dir.path().join(path)).unwrap();
+    (metadata.uid(), metadata.gid())
+}
+
+#[test]
+#[ignore]
+#[cfg(unix)]
+fn uncompress_archive_owner_lookup() {
+    let fixture = "tests/fixtures/owner-names.tar";
+    assert_eq!(
+        extract_owner(fixture, "owned", ExtractOptions::new()),
+        (0, 0)
+    );
+    assert_eq!(
+        extract_owner(
+            fixture,
+            "owned",
+            ExtractOptions::new().owner_lookup(OwnerLookup::Ids)
+        ),
+        (1234, 1234)
+    );
+}
+
+#[test]
+#[ignore]
+#[cfg(unix)]
+fn uncompress_archive_owner_mapping() {
+    let fixture = "tests/fixtures/tree.tar";
+    let leaf = "tree/branch1/leaf";
+    let numeric = || ExtractOptions::new().owner_lookup(OwnerLookup::Ids);
+
+    assert_eq!(
+        extract_owner(
+            fixture,
+            leaf,
+            numeric().owner_mapping(OwnerMapping::Fixed { uid: 42, gid: 43 })
+        ),
+        (42, 43)
+    );
+    assert_eq!(
+        extract_owner(
+            fixture,
+            leaf,
+            numeric().owner_mapping(OwnerMapping::Ranges {
+                uids: vec![IdRange::new(0, 100000, 65536)],
+                gids: vec![IdRange::new(0, 200000, 10), IdRange::new(10, 300000, 65526)],
+            })
+        ),
+        (101000, 300990)
+    );
+    assert_eq!(
+        extract_owner(
+            fixture,
+            leaf,
+            numeric().owner_mapping(OwnerMapping::Callback(Box::new(|uid, gid, uname, _| {
+                assert_eq!(uname, Some("jonathas"));
+                (uid + 1, gid + 2)
+            })))
+        ),
+        (1001, 1002)
+    );
+    // Names are resolved before the mapping.
+    assert_eq!(
+        extract_owner(
+            "tests/fixtures/owner-names.tar",
+            "owned",
+            ExtractOptions::new().owner_mapping(OwnerMapping::Ranges {
+                uids: vec![IdRange::new(0, 100000, 65536)],
+                gids: vec![IdRange::new(0, 100000, 65536)],
+            })
+        ),
+        (100000, 100000)
+    );
+}
+
+#[test]
+fn uncompress_archive_unmapped_owner() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let options = ExtractOptions::new()
+        .ownership(Ownership::Preserve)
+        .owner_lookup(OwnerLookup::Ids)
+        .owner_mapping(OwnerMapping::Ranges {
+            uids: vec![IdRange::new(0, 100000, 1000)],
+            gids: vec![IdRange::new(0, 100000, 65536)],
+        });
+    let result = uncompress_archive_with_options(
+        std::fs::File::open("tests/fixtures/tree.tar").unwrap(),
+        dir.path(),
+        options,
+    );
+
+    match result {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_owner_mapping_to_current_user() {
+    use std::os::unix::fs::MetadataExt;
+
+    // Mapping to the owner of a new file needs no privileges.
+    let probe = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let metadata = std::fs::metadata(probe.path()).unwrap();
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+    assert_eq!(
+        extract_owner(
+            "tests/fixtures/tree.tar",
+            "tree/branch1/leaf",
+            ExtractOptions::new()
+                .owner_lookup(OwnerLookup::Ids)
+                .owner_mapping(OwnerMapping::Ranges {
+                    uids: vec![IdRange::new(1000, uid.into(), 1)],
+                    gids: vec![IdRange::new(1000, gid.into(), 1)],
+                })
+        ),
+        (uid, gid)
+    );
+
+    // Ids past `uid_t` would be truncated, e.g. 2^32 to root.
+    let past_u32 = 1 << 32;
+    let mappings = [
+        OwnerMapping::Ranges {
+            uids: vec![IdRange::new(0, past_u32, 65536)],
+            gids: vec![IdRange::new(0, gid.into(), 65536)],
+        },
+        OwnerMapping::Fixed {
+            uid: uid.into(),
+            gid: past_u32,
+        },
+        OwnerMapping::Callback(Box::new(move |_, _, _, _| (past_u32, past_u32))),
+    ];
+    for mapping in mappings {
+        let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+        let options = ExtractOptions::new()
+            .ownership(Ownership::Preserve)
+            .owner_lookup(OwnerLookup::Ids)
+            .owner_mapping(mapping);
+        let result = uncompress_archive_with_options(
+            std::fs::File::open("tests/fixtures/tree.tar").unwrap(),
+            dir.path(),
+            options,
+        );
+        match result {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(std::fs::symlink_metadata(dir.path().join("tree")).is_err());
+    }
+}
+
+#[cfg(unix)]
+fn extract_modes(fixture: &str, options: ExtractOptions<'_>, paths: &[&str]) -> Vec<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open(fixture).unwrap();
+    uncompress_archive_with_options(source, dir.path(), options).unwrap();
+    paths
+        .iter()
+        .map(|path| {
+            std::fs::metadata(dir.path().join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        })
+        .collect()
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_umask_and_forced_modes() {
+    let fixture = "tests/fixtures/tree.tar";
+    let paths = ["tree/branch1", "tree/branch1/leaf"];
+
+    assert_eq!(
+        extract_modes(fixture, ExtractOptions::new(), &paths),
+        [0o775, 0o664]
+    );
+    assert_eq!(
+        extract_modes(fixture, ExtractOptions::new().umask(0o027), &paths),
+        [0o750, 0o640]
+    );
+    assert_eq!(
+        extract_modes(
+            fixture,
+            ExtractOptions::new().dir_mode(0o711).file_mode(0o604),
+            &paths
+        ),
+        [0o711, 0o604]
+    );
+    assert_eq!(
+        extract_modes(
+            fixture,
+            ExtractOptions::new()
+                .dir_mode(0o777)
+                .file_mode(0o666)
+                .umask(0o022),
+            &paths
+        ),
+        [0o755, 0o644]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_no_world_writable() {
+    let options = ExtractOptions::new()
+        .special_files(SpecialFilePolicy::Skip)
+        .no_world_writable(true);
+
+    assert_eq!(
+        extract_modes(
+            "tests/fixtures/special.tar",
+            options,
+            &["special", "special/sticky"]
+        ),
+        [0o755, 0o1775]
+    );
+}
+
+#[cfg(unix)]
+fn assert_readonly_tree(dest: &Path) {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    for (path, mode, mtime) in [
+        ("readonly", 0o555, 1500000000),
+        ("readonly/nested", 0o555, 1500000001),
+        ("readonly/nested/file", 0o444, 1600000000),
+    ] {
+        let metadata = std::fs::metadata(dest.join(path)).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, mode, "{}", path);
+        assert_eq!(metadata.mtime(), mtime, "{}", path);
+    }
+    assert_eq!(
+        std::fs::read(dest.join("readonly/nested/file")).unwrap(),
+        b"read only\n"
+    );
+
+    // Let the temporary directory be removed.
+    for path in ["readonly", "readonly/nested"] {
+        std::fs::set_permissions(dest.join(path), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_archive_readonly_directories() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/readonly.tar").unwrap();
+    uncompress_archive(source, dir.path(), Ownership::Ignore).unwrap();
+    assert_readonly_tree(dir.path());
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/readonly.tar").unwrap();
+    uncompress_archive_into(source, &mut DiskSink::new(dir.path())).unwrap();
+    assert_readonly_tree(dir.path());
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let handle = std::fs::File::open(dir.path()).unwrap();
+    let source = std::fs::File::open("tests/fixtures/readonly.tar").unwrap();
+    uncompress_archive_at(source, &handle, Ownership::Ignore).unwrap();
+    assert_readonly_tree(dir.path());
+}
+
+#[tokio::test]
+#[cfg(all(unix, feature = "tokio_support"))]
+async fn uncompress_archive_readonly_directories_async() {
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = tokio::fs::File::open("tests/fixtures/readonly.tar")
+        .await
+        .unwrap();
+    tokio_support::uncompress_archive(source, dir.path(), Ownership::Ignore)
+        .await
+        .unwrap();
+    assert_readonly_tree(dir.path());
+}
+
+fn sparse_content() -> Vec<u8> {
+    let mut content = vec![0; 1024 * 1024];
+    content[..6].copy_from_slice(b"start\n");
+    content[256 * 1024..256 * 1024 + 7].copy_from_slice(b"middle\n");
+    content
+}
+
+#[test]
+fn sparse_entry_chunks_carry_offsets() {
+    let source = std::fs::File::open("tests/fixtures/sparse.tar").unwrap();
+    let mut content = Vec::new();
+    let mut offsets = Vec::new();
+    for item in ArchiveIteratorBuilder::new(source).build().unwrap() {
+        match item {
+            ArchiveContents::StartOfEntry(_, stat) => content.resize(stat.st_size as usize, 0),
+            ArchiveContents::DataChunk(chunk, offset) => {
+                let offset = offset as usize;
+                content[offset..offset + chunk.len()].copy_from_slice(&chunk);
+                offsets.push(offset);
+            }
+            ArchiveContents::EndOfEntry => {}
+            ArchiveContents::Err(e) => panic!("{}", e),
+        }
+    }
+
+    // The entry ends with a hole, marked by an empty chunk.
+    assert_eq!(offsets, [0, 256 * 1024, 1024 * 1024]);
+    assert_eq!(content, sparse_content());
+}
+
+#[test]
+fn uncompress_sparse_file() {
+    // Holes are written as zeros to plain writers.
+    let source = std::fs::File::open("tests/fixtures/sparse.tar").unwrap();
+    let mut target = Vec::new();
+    let written = uncompress_archive_file(source, &mut target, "sparse.bin").unwrap();
+    assert_eq!(written, 1024 * 1024);
+    assert_eq!(target, sparse_content());
+
+    let source = std::fs::File::open("tests/fixtures/sparse.tar").unwrap();
+    let mut target = Cursor::new(Vec::new());
+    let written = uncompress_archive_file_sparse(source, &mut target, "sparse.bin").unwrap();
+    assert_eq!(written, 1024 * 1024);
+    assert_eq!(target.into_inner(), sparse_content());
+}
+
+#[test]
+#[cfg(unix)]
+fn uncompress_sparse_file_creates_holes() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let path = dir.path().join("sparse.bin");
+    let source = std::fs::File::open("tests/fixtures/sparse.tar").unwrap();
+    let target = std::fs::File::create(&path).unwrap();
+    uncompress_archive_file_sparse(source, target, "sparse.bin").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), sparse_content());
+    // Allocated in 512 byte blocks; the data spans two file system blocks.
+    assert!(std::fs::metadata(&path).unwrap().blocks() * 512 < 1024 * 1024);
+
+    let dir = tempfile::TempDir::new().expect("Failed to create the tmp directory");
+    let source = std::fs::File::open("tests/fixtures/sparse.tar").unwrap();
+    uncompress_archive_with_options(source, dir.path(), ExtractOptions::new().sparse(true))
+        .unwrap();
+    let path = dir.path().join("sparse.bin");
+    assert_eq!(std::fs::read(&path).unwrap(), sparse_content());
+    assert!(std::fs::metadata(&path).unwrap().blocks() * 512 < 1024 * 1024);
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn open_archive_file_futures() {
+    use futures_util::io::AsyncReadExt;
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/tree.tar")
+            .await
+            .unwrap();
+        let mut content = String::new();
+        futures_support::open_archive_file(source, "tree/branch2/leaf")
+            .read_to_string(&mut content)
+            .await
+            .unwrap();
+        assert_eq!(content, "Goodbye World\n");
+    });
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn open_archive_file_tokio() {
+    use futures_util::io::AsyncReadExt;
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/sparse.tar")
+        .await
+        .unwrap();
+    let mut content = Vec::new();
+    tokio_support::open_archive_file(source, "sparse.bin")
+        .compat()
+        .read_to_end(&mut content)
+        .await
+        .unwrap();
+    assert_eq!(content, sparse_content());
+
+    // Reading only the start and dropping the reader stops the extraction.
+    let source = tokio::fs::File::open("tests/fixtures/sparse.tar")
+        .await
+        .unwrap();
+    let mut start = [0; 6];
+    tokio_support::open_archive_file(source, "sparse.bin")
+        .compat()
+        .read_exact(&mut start)
+        .await
+        .unwrap();
+    assert_eq!(&start, b"start\n");
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn open_archive_file_with_password_tokio() {
+    use futures_util::io::AsyncReadExt;
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/with-password.zip")
+        .await
+        .unwrap();
+    let mut content = String::new();
+    tokio_support::open_archive_file_with_options(
+        source,
+        "with-password/file1.txt",
+        password_read_options(&["123"]),
+    )
+    .compat()
+    .read_to_string(&mut content)
+    .await
+    .unwrap();
+    assert_eq!(content, "its encrypted file");
+
+    // Without the password, the entry cannot be read.
+    let source = tokio::fs::File::open("tests/fixtures/with-password.zip")
+        .await
+        .unwrap();
+    tokio_support::open_archive_file(source, "with-password/file1.txt")
+        .compat()
+        .read_to_end(&mut Vec::new())
+        .await
+        .unwrap_err();
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn open_archive_file_with_password_futures() {
+    use futures_util::io::AsyncReadExt;
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/with-password.zip")
+            .await
+            .unwrap();
+        let mut content = String::new();
+        futures_support::open_archive_file_with_options(
+            source,
+            "with-password/file2.txt",
+            password_read_options(&["123"]),
+        )
+        .read_to_string(&mut content)
+        .await
+        .unwrap();
+        assert_eq!(content, "file 2 in archive encrypted!");
+    });
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn open_archive_file_missing_tokio() {
+    use futures_util::io::AsyncReadExt;
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/tree.tar")
+        .await
+        .unwrap();
+    let err = tokio_support::open_archive_file(source, "tree/missing")
+        .compat()
+        .read_to_end(&mut Vec::new())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn iterate_archive_entries_futures() {
+    use futures_util::{future::FutureExt, io::AsyncReadExt, stream::StreamExt};
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/tree.tar")
+            .await
+            .unwrap();
+        let mut entries = futures_support::ArchiveIteratorBuilder::new(source).build_entries();
+
+        let mut contents = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry.unwrap();
+            // The next entry waits for the reader of this one.
+            assert!(entries.next().now_or_never().is_none());
+
+            let mut content = String::new();
+            entry.reader.read_to_string(&mut content).await.unwrap();
+            contents.push((entry.meta.path, content));
+        }
+
+        assert_eq!(
+            contents,
+            [
+                ("tree/".to_string(), String::new()),
+                ("tree/branch1/".to_string(), String::new()),
+                ("tree/branch1/leaf".to_string(), "Hello World\n".to_string()),
+                ("tree/branch2/".to_string(), String::new()),
+                (
+                    "tree/branch2/leaf".to_string(),
+                    "Goodbye World\n".to_string()
+                ),
+            ],
+        );
+    });
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn iterate_archive_entries_dropping_readers_futures() {
+    use futures_util::{io::AsyncReadExt, stream::StreamExt};
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/tree.tar")
+            .await
+            .unwrap();
+        let mut entries = futures_support::ArchiveIteratorBuilder::new(source).build_entries();
+
+        let mut contents = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry.unwrap();
+            // Stop reading the first leaf half way, skip the other entries.
+            if entry.meta.path == "tree/branch1/leaf" {
+                let mut start = [0; 5];
+                entry.reader.read_exact(&mut start).await.unwrap();
+                contents.push(String::from_utf8(start.to_vec()).unwrap());
+            }
+        }
+        assert_eq!(contents, ["Hello"]);
+    });
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn iterate_archive_entries_sparse_tokio() {
+    use futures_util::{io::AsyncReadExt, stream::StreamExt};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let source = tokio::fs::File::open("tests/fixtures/sparse.tar")
+        .await
+        .unwrap();
+    let mut entries = tokio_support::ArchiveIteratorBuilder::new(source).build_entries();
+
+    let entry = entries.next().await.unwrap().unwrap();
+    assert_eq!(entry.meta.path, "sparse.bin");
+    assert_eq!(entry.meta.stat.st_size, 1024 * 1024);
+    let mut content = Vec::new();
+    // Read through the tokio implementation.
+    entry
+        .reader
+        .compat()
+        .read_to_end(&mut content)
+        .await
+        .unwrap();
+    assert_eq!(content, sparse_content());
+
+    assert!(entries.next().await.is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn iterate_archive_entries_links_tokio() {
+    use futures_util::{io::AsyncReadExt, stream::StreamExt};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    // Hard links carry no data, and ISO 9660 directories report the size of
+    // their extent: no zeros are made up for either.
+    for fixture in ["tests/fixtures/links.tar", "tests/fixtures/links.iso.gz"] {
+        let source = std::fs::File::open(fixture).unwrap();
+        let mut expected = Vec::new();
+        for item in ArchiveIteratorBuilder::new(source).build().unwrap() {
+            match item {
+                ArchiveContents::StartOfEntry(name, _) => expected.push((name, Vec::new())),
+                ArchiveContents::DataChunk(chunk, offset) => {
+                    let content = &mut expected.last_mut().unwrap().1;
+                    content.resize(offset as usize, 0);
+                    content.extend_from_slice(&chunk);
+                }
+                ArchiveContents::EndOfEntry => {}
+                ArchiveContents::Err(e) => panic!("{}", e),
+            }
+        }
+        assert!(expected
+            .iter()
+            .any(|(name, content)| name == "links/hardlink" && content.is_empty()));
+
+        let source = tokio::fs::File::open(fixture).await.unwrap();
+        let mut entries = tokio_support::ArchiveIteratorBuilder::new(source).build_entries();
+        let mut contents = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let entry = entry.unwrap();
+            let mut content = Vec::new();
+            entry
+                .reader
+                .compat()
+                .read_to_end(&mut content)
+                .await
+                .unwrap();
+            contents.push((entry.meta.path, content));
+        }
+        assert_eq!(contents, expected);
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio_support")]
+async fn iterate_archive_raw_format_tokio() {
+    use futures_util::stream::StreamExt;
+
+    let source = Cursor::new(NON_ARCHIVE_BYTES);
+    let names: Vec<String> = tokio_support::ArchiveIteratorBuilder::new(source)
+        .raw_format(true)
+        .build()
+        .filter_map(|c| async move {
+            match c {
+                ArchiveContents::StartOfEntry(name, _) => Some(name),
+                _ => None,
+            }
+        })
+        .collect()
+        .await;
+    assert_eq!(names, vec!["data".to_string()]);
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn iterate_archive_mtree_format_opt_out_futures() {
+    use futures_util::stream::StreamExt;
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/file.txt.gz")
+            .await
+            .unwrap();
+        let mut iter = futures_support::ArchiveIteratorBuilder::new(source)
+            .mtree_format(false)
+            .build();
+
+        let mut saw_err = false;
+        while let Some(content) = iter.next().await {
+            saw_err |= matches!(content, ArchiveContents::Err(_));
+        }
+        assert!(saw_err);
+    });
+}
+
+#[test]
+#[cfg(feature = "futures_support")]
+fn iterate_archive_matcher_futures() {
+    use futures_util::stream::StreamExt;
+
+    smol::block_on(async {
+        let source = smol::fs::File::open("tests/fixtures/tree.tar")
+            .await
+            .unwrap();
+        let mut iter = futures_support::ArchiveIteratorBuilder::new(source)
+            .matcher(EntryMatcher::new().include("tree/*/leaf"))
+            .build();
+
+        let mut names = Vec::new();
+        while let Some(content) = iter.next().await {
+            if let ArchiveContents::StartOfEntry(name, _) = content {
+                names.push(name);
+            }
+        }
+        assert_eq!(names, ["tree/branch1/leaf", "tree/branch2/leaf"]);
+    });
+}